@@ -0,0 +1,46 @@
+//! Per-call overhead of a clock shared by many threads polling it in a tight loop.
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::{
+    sync::{Arc, Barrier},
+    time::{Duration, Instant},
+};
+
+const THREADS: usize = 16;
+
+fn concurrent_get_value(c: &mut Criterion) {
+    let server = clockkit::Server::new(4463);
+    server.start();
+    let clock = Arc::new(clockkit::Config::default().port(4463).build_clock());
+    clock.start();
+    clock
+        .wait_until_synchronized(Duration::from_secs(5))
+        .expect("clock did not synchronize");
+
+    c.bench_function("get_value_raw, 16 threads", |b| {
+        b.iter_custom(|iters| {
+            let barrier = Arc::new(Barrier::new(THREADS + 1));
+            let threads: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    let clock = Arc::clone(&clock);
+                    let barrier = Arc::clone(&barrier);
+                    std::thread::spawn(move || {
+                        barrier.wait();
+                        for _ in 0..iters {
+                            let _ = criterion::black_box(clock.get_value_raw());
+                        }
+                    })
+                })
+                .collect();
+            barrier.wait();
+            let start = Instant::now();
+            for t in threads {
+                t.join().unwrap();
+            }
+            // Every thread did `iters` calls, report the time per call.
+            start.elapsed() / THREADS as u32
+        })
+    });
+}
+
+criterion_group!(benches, concurrent_get_value);
+criterion_main!(benches);
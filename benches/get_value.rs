@@ -0,0 +1,32 @@
+//! Baseline of the timestamp path: the FFI call, and the conversion to `DateTime` on its own.
+use clockkit::Timestamp;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::time::Duration;
+
+fn get_value(c: &mut Criterion) {
+    let server = clockkit::Server::new(4464);
+    server.start();
+    let clock = clockkit::Config::default().port(4464).build_clock();
+    clock.start();
+    clock
+        .wait_until_synchronized(Duration::from_secs(5))
+        .expect("clock did not synchronize");
+
+    c.bench_function("get_value", |b| b.iter(|| black_box(clock.get_value())));
+    c.bench_function("get_value_raw", |b| {
+        b.iter(|| black_box(clock.get_value_raw()))
+    });
+}
+
+fn conversion(c: &mut Criterion) {
+    let ts = Timestamp::from_micros(1_700_000_000_123_456);
+    c.bench_function("Timestamp::to_datetime", |b| {
+        b.iter(|| black_box(black_box(ts).to_datetime()))
+    });
+    c.bench_function("Timestamp::to_system_time", |b| {
+        b.iter(|| black_box(black_box(ts).to_system_time()))
+    });
+}
+
+criterion_group!(benches, get_value, conversion);
+criterion_main!(benches);
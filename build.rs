@@ -1,13 +1,50 @@
 use std::io;
 use std::path::{Path, PathBuf};
 
-#[cfg(feature = "build_server")]
+#[cfg(any(feature = "build_server", feature = "check_bundle"))]
 use std::process::Command;
 
+/// The upstream clockkit commit the bundled sources are taken from, see README.md.
+#[cfg(feature = "check_bundle")]
+const BUNDLED_COMMIT: &str = "a7856021da846988d022879c95ec745caa5ae9e8";
+
+/// Fail with a hint instead of a compiler error if the bundled sources are missing, e.g. because
+/// `include/ClockKit` is an uninitialized submodule, and warn if a checkout is at another commit.
+#[cfg(feature = "check_bundle")]
+fn check_bundle(bundle_dir: &Path, ckfiles: &[PathBuf]) -> io::Result<()> {
+    let missing: Vec<_> = ckfiles.iter().filter(|f| !f.exists()).collect();
+    if !missing.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "clockkit sources missing: {missing:?}, run `git submodule update --init` to fetch \
+                 them into {}",
+                bundle_dir.display()
+            ),
+        ));
+    }
+    // Only a checkout of its own has a commit to compare, the published crate has none.
+    if bundle_dir.join(".git").exists() {
+        let output = Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(bundle_dir)
+            .output()?;
+        let commit = String::from_utf8_lossy(&output.stdout);
+        if output.status.success() && commit.trim() != BUNDLED_COMMIT {
+            println!(
+                "cargo:warning=check_bundle: {} is at {}, expected {BUNDLED_COMMIT}",
+                bundle_dir.display(),
+                commit.trim()
+            );
+        }
+    }
+    Ok(())
+}
+
 fn main() -> io::Result<()> {
     let bundle_dir = Path::new("include/ClockKit");
 
-    let ckfiles = vec![
+    let ckfiles = [
         "bridge.cpp",
         "ClockPacket.cpp",
         "ClockClient.cpp",
@@ -21,32 +58,74 @@ fn main() -> io::Result<()> {
     .map(|f| bundle_dir.join(f))
     .collect::<Vec<PathBuf>>();
 
-    cxx_build::bridge("src/lib.rs")
+    #[cfg(feature = "check_bundle")]
+    check_bundle(bundle_dir, &ckfiles)?;
+
+    // Once any rerun-if directive is printed, and cc prints some, Cargo no longer reruns on
+    // every package change, so list everything the C++ build depends on.
+    for entry in std::fs::read_dir(bundle_dir)? {
+        let path = entry?.path();
+        if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("cpp" | "h" | "hpp")
+        ) {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // Build scripts run on the host, so ask Cargo about the target instead of using cfg!.
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let target_env = std::env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+    let msvc = target_env == "msvc";
+
+    let mut build = cxx_build::bridge("src/lib.rs");
+    build
         .files(ckfiles)
         .cpp(true)
-        .flag("--std=c++17")
-        .flag("-static")
+        .flag(if msvc { "/std:c++17" } else { "--std=c++17" })
         .warnings(false)
-        .extra_warnings(false)
-        .compile("libclockkit.a");
+        .extra_warnings(false);
+    if target_os == "linux" {
+        build.flag("-static");
+    }
+    build.compile("libclockkit.a");
 
     // Build the server for testing, unfortunately this clutters the src directory with object
-    // files
+    // files. The bundle doesn't ship a Makefile for every target, the doctests use the
+    // in-process `clockkit::Server` and don't need it.
     #[cfg(feature = "build_server")]
     {
-        Command::new("make")
-            .arg("ckserver")
-            .current_dir(&bundle_dir)
-            .status()
-            .unwrap();
+        if bundle_dir.join("Makefile").exists() {
+            let status = Command::new("make")
+                .arg("ckserver")
+                .current_dir(bundle_dir)
+                .status()?;
+            if !status.success() {
+                println!("cargo:warning=build_server: building ckserver failed with {status}");
+            }
+        } else {
+            println!(
+                "cargo:warning=build_server: no Makefile in {}, skipping ckserver",
+                bundle_dir.display()
+            );
+        }
     }
 
     println!("cargo:rustc-link-search=native={}", bundle_dir.display());
     println!("cargo:rustc-link-lib=static=clockkit");
 
-    // Add dynamically linked libraries clockkit depends on
-    println!("cargo:rustc-flags=-l dylib=stdc++");
-    println!("cargo:rustc-flags=-l dylib=pthread");
-    println!("cargo:rustc-flags=-l dylib=dl");
+    // Add dynamically linked libraries clockkit depends on. On macOS pthread and dl are part of
+    // libSystem, MSVC links its C++ runtime by itself.
+    let libs: &[&str] = match (target_os.as_str(), msvc) {
+        ("macos" | "ios", _) => &["c++"],
+        ("windows", true) => &["ws2_32"],
+        ("windows", false) => &["stdc++", "pthread", "ws2_32"],
+        _ => &["stdc++", "pthread", "dl"],
+    };
+    for lib in libs {
+        println!("cargo:rustc-link-lib=dylib={lib}");
+    }
     Ok(())
 }
@@ -1,27 +1,63 @@
 use argh::FromArgs;
-use std::{path::PathBuf, time::Duration};
+use std::{path::PathBuf, process::ExitCode, time::Duration};
 
 #[derive(FromArgs)]
 /// Run a clockkit client.
 struct CmdlineConf {
-    /// configuration file.
+    /// configuration file, by default clockkit.conf in the config directory.
     #[argh(positional)]
-    config_file: PathBuf,
+    config_file: Option<PathBuf>,
+    /// measure the sync quality for this many seconds, print a summary and exit.
+    #[argh(option)]
+    measure: Option<u64>,
+    /// with --measure, fail if the absolute offset exceeded this many μs, 1000 by default.
+    #[argh(option, default = "1000")]
+    max_offset: u64,
 }
 
-fn main() {
+fn main() -> ExitCode {
     let args: CmdlineConf = argh::from_env();
-    let config = clockkit::Config::from_config_file(args.config_file).unwrap();
+    let config = match args.config_file {
+        Some(path) => clockkit::Config::from_config_file(path),
+        None => clockkit::Config::from_default_location(),
+    }
+    .unwrap();
     let plc = config.build_clock();
 
+    if let Some(secs) = args.measure {
+        let report = match plc.measure(Duration::from_secs(secs)) {
+            Ok(report) => report,
+            Err(e) => {
+                println!("Measurement failed: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        println!(
+            "Offset: mean {:.1} μs, max {} μs, stddev {:.1} μs",
+            report.offsets.mean,
+            report.max_abs_offset(),
+            report.offsets.stddev
+        );
+        println!("RTT: mean {:?}, max {:?}", report.mean_rtt, report.max_rtt);
+        println!(
+            "Updates: {}, timeouts: {}, samples out of sync: {}",
+            report.updates, report.timeouts, report.out_of_sync
+        );
+        if report.max_abs_offset() > args.max_offset {
+            println!("Offset exceeds {} μs.", args.max_offset);
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+
     plc.start();
 
-    loop {
-        if let Ok(ts) = plc.get_value() {
+    for value in plc.iter(Duration::from_millis(500)) {
+        if let Ok(ts) = value {
             println!("Value: {:?}", ts);
         } else {
             println!("Out of sync.");
         }
-        std::thread::sleep(Duration::from_millis(500));
     }
+    ExitCode::SUCCESS
 }
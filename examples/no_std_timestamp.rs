@@ -0,0 +1,7 @@
+//! Build check that the timestamp math compiles without `std`, e.g. for embedded clients.
+#![no_std]
+
+#[path = "../src/timestamp.rs"]
+mod timestamp;
+
+pub use timestamp::Timestamp;
@@ -0,0 +1,37 @@
+//! Serve the metrics of a clock at http://127.0.0.1:9898/metrics.
+
+use prometheus::{Encoder, TextEncoder};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+};
+
+fn main() {
+    let clock = clockkit::Config::from_default_location()
+        .unwrap_or_default()
+        .build_clock();
+    let registry = prometheus::Registry::new();
+    clockkit::register_metrics(&clock, &registry).unwrap();
+    clock.start();
+
+    let listener = TcpListener::bind("127.0.0.1:9898").unwrap();
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else { continue };
+        // Any request gets the metrics, only the request line is read.
+        let mut request = String::new();
+        if BufReader::new(&stream).read_line(&mut request).is_err() {
+            continue;
+        }
+        let encoder = TextEncoder::new();
+        let mut body = Vec::new();
+        encoder.encode(&registry.gather(), &mut body).unwrap();
+        let header = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            encoder.format_type(),
+            body.len()
+        );
+        let _ = stream
+            .write_all(header.as_bytes())
+            .and_then(|()| stream.write_all(&body));
+    }
+}
@@ -25,6 +25,19 @@ class Clock {
         return diff(getValue(), c.getValue());
     }
 
+    // Round trip time of the previous getPhase(), for clocks that are asked over a network.
+    virtual dur rtt() const
+    {
+        return dur(0);
+    }
+
+    // The remote clock's time as reported in the previous successful getPhase(),
+    // tpInvalid for local clocks.
+    virtual tp serverTime() const
+    {
+        return tpInvalid;
+    }
+
     virtual ~Clock() = default;
 
     // Cleanly kill a ClockServer or ClockClient.
@@ -8,18 +8,54 @@ using namespace std::chrono;
 
 namespace dex {
 
+// kissnet aborts when constructing a socket for an endpoint that doesn't resolve.
+static bool resolves(const kissnet::endpoint& addr_port)
+{
+    addrinfo hints = {};
+    hints.ai_family = AF_UNSPEC;
+    hints.ai_socktype = SOCK_DGRAM;
+    addrinfo* results = nullptr;
+    if (getaddrinfo(addr_port.address.c_str(), std::to_string(addr_port.port).c_str(), &hints, &results) != 0)
+        return false;
+    freeaddrinfo(results);
+    return true;
+}
+
 ClockClient::ClockClient(kissnet::endpoint addr_port)
-    : timeout_(1000)
+    : timeout_(dur(1000))
     , rtt_(0u)
+    , serverTime_(tpInvalid)
     , sequence_(0)
+    , lastSequence_(noSequence)
     , acknowledge_(false)
-    , socket_(addr_port)
+    , resolved_(resolves(addr_port))
+    // A placeholder that resolves, never sent to.
+    , socket_(resolved_ ? addr_port : kissnet::endpoint("127.0.0.1", 0))
 {
     kissnet_init();
+    if (!resolved_)
+        cerr << "ClockClient failed to resolve " << addr_port.address << "\n";
+}
+
+bool ClockClient::bindLocal(const std::string& address)
+{
+    if (socket_.bind_local(kissnet::endpoint(address, 0)))
+        return true;
+    cerr << "ClockClient failed to bind to " << address << "\n";
+    return false;
+}
+
+void ClockClient::tracePacket(bool sent, const ClockPacket& packet)
+{
+    std::lock_guard<std::mutex> lock(traceMutex_);
+    if (trace_)
+        trace_(sent, packet);
 }
 
 bool ClockClient::sendPacket(const ClockPacket& packet)
 {
+    if (!resolved_)
+        return false;
     ClockPacket::packetbuf buffer;
     packet.write(buffer);
 #ifdef DEBUG
@@ -38,6 +74,7 @@ bool ClockClient::sendPacket(const ClockPacket& packet)
     cerr << "sent\n";
     packet.print();
 #endif
+    tracePacket(true, packet);
     return true;
 }
 
@@ -45,7 +82,7 @@ ClockPacket ClockClient::receivePacket(Clock& clock)
 {
     ClockPacket::packetbuf buffer;
     // getTimeout() isn't invalid.
-    const auto timeoutMsec = std::max(1, getTimeout() / 1000);
+    const auto timeoutMsec = std::max<int64_t>(1, getTimeout() / 1000);
     while (true) {
         switch (socket_.select(kissnet::fds_read, timeoutMsec).value) {
             case kissnet::socket_status::errored:
@@ -76,6 +113,12 @@ ClockPacket ClockClient::receivePacket(Clock& clock)
 #ifdef DEBUG
         cerr << "got " << packet.getTypeName() << "\n";
 #endif
+        {
+            ClockPacket traced(packet);
+            traced.setClientReceiveTime(now);
+            tracePacket(false, traced);
+        }
+        lastSequence_ = packet.getSeqnum();
         if (packet.getType() == ClockPacket::KILL) {
             exit(0);  // todo: kill just the ClockClient, not the entire process?  That's too harsh.
         }
@@ -95,13 +138,15 @@ ClockPacket ClockClient::receivePacket(Clock& clock)
         packet.setClientReceiveTime(now);
         const auto rtt = packet.rtt();
         // timeout_ isn't invalid.
-        if (rtt == durInvalid || rtt > timeout_) {
+        const auto timeout = timeout_.load();
+        if (rtt == durInvalid || rtt > timeout) {
 #ifdef DEBUG
-            cerr << "ignoring reply that arrived more than " << UsecFromDur(timeout_) << " μs later\n";
+            cerr << "ignoring reply that arrived more than " << UsecFromDur(timeout) << " μs later\n";
 #endif
             return ClockPacket();
         }
         rtt_ = rtt;
+        serverTime_ = packet.getServerReplyTime();
         return packet;
     }
 }
@@ -116,6 +161,15 @@ tp ClockClient::getValue()
     // primary clock = secondary clock + phase
 }
 
+ClockPacket ClockClient::queryPacket()
+{
+    Clock& baseClock = SystemClock::instance();
+    ++sequence_ %= 250;  // One byte.
+    if (!sendPacket(ClockPacket(ClockPacket::REQUEST, sequence_, baseClock.getValue())))
+        return ClockPacket();
+    return receivePacket(baseClock);
+}
+
 // We can't use a default value "bool acknowledge = acknowledge_"
 // because the base class's signature for getPhase has only the first arg.
 // Always false, while it's called from only ClockClient::getValue().
@@ -1,5 +1,8 @@
 #pragma once
+#include <atomic>
+#include <functional>
 #include <limits>
+#include <mutex>
 
 #include "Clock.h"
 #include "ClockPacket.h"
@@ -21,6 +24,7 @@ namespace dex {
 class ClockClient : public Clock {
    public:
     // Connect to a host and port.
+    // If the host doesn't resolve, the client never gets a reply instead of aborting.
     explicit ClockClient(kissnet::endpoint);
 
     ~ClockClient()
@@ -31,15 +35,20 @@ class ClockClient : public Clock {
     ClockClient(const ClockClient&) = delete;
     ClockClient& operator=(const ClockClient&) = delete;
 
+    // Send from the local IP address, instead of the one picked by the OS's routing.
+    // Returns false if the address can't be bound.
+    bool bindLocal(const std::string& address);
+
     // Get the ClockServer's "current" time.
     // Slower and less accurate than getPhase().
     // Calls getPhase(SystemClock::instance()).
     // Returns "invalid" on error.
     tp getValue();
 
-    int getTimeout() const
+    // Safe to call while another thread uses the client, the next request uses the new value.
+    int64_t getTimeout() const
     {
-        return UsecFromDur(timeout_);
+        return UsecFromDur(timeout_.load());
     }
     void setTimeout(int64_t usec)
     {
@@ -52,6 +61,19 @@ class ClockClient : public Clock {
         return rtt_;
     }
 
+    tp serverTime() const
+    {
+        return serverTime_;
+    }
+
+    // The sequence number of the last packet received, including ignored ones,
+    // or noSequence before the first.  Safe to call while another thread uses the client.
+    static constexpr uint32_t noSequence = std::numeric_limits<uint32_t>::max();
+    uint32_t getLastSequence() const
+    {
+        return lastSequence_;
+    }
+
     // If true, when getPhase() is called externally (from PhaseLockedClock.cpp)
     // instead of internally by getValue(),
     // it finishes by sending the server an ACKNOWLEDGE packet,
@@ -69,6 +91,21 @@ class ClockClient : public Clock {
         return getPhase(clock, acknowledge_);
     }
 
+    // Send a REQUEST and return the server's REPLY, timestamped with SystemClock.
+    // For inspecting the protocol.  Doesn't acknowledge the reply.
+    // On error, the returned packet's type is INVALID.
+    ClockPacket queryPacket();
+
+    // Called with every packet sent (true) or received (false), for debugging the protocol.
+    // Received packets are passed on before checking their type and sequence number.
+    // An empty function disables tracing.  Safe to call while another thread uses the client.
+    using PacketTrace = std::function<void(bool sent, const ClockPacket&)>;
+    void setPacketTrace(PacketTrace trace)
+    {
+        std::lock_guard<std::mutex> lock(traceMutex_);
+        trace_ = std::move(trace);
+    }
+
     // Kill the connected ClockServer.
     void die()
     {
@@ -76,11 +113,18 @@ class ClockClient : public Clock {
     }
 
    private:
-    dur timeout_;  // The max error on phase calculations.
+    std::atomic<dur> timeout_;  // The max error on phase calculations.
     dur rtt_;      // The previous call's round trip time.
+    tp serverTime_;  // The server's time in the previous call's reply.
     seqnum sequence_;
+    std::atomic<uint32_t> lastSequence_;
     bool acknowledge_;
+    bool resolved_;  // Before socket_, which depends on it.
     kissnet::udp_socket socket_;
+    PacketTrace trace_;
+    std::mutex traceMutex_;
+
+    void tracePacket(bool sent, const ClockPacket&);
 
     bool sendPacket(const ClockPacket&);
 
@@ -70,7 +70,6 @@ class ClockPacket {
         return type_ == INVALID;
     }
 
-#ifdef UNUSED
     tp getClientRequestTime() const
     {
         return clientRequestTime_;
@@ -88,7 +87,6 @@ class ClockPacket {
     {
         return clientReceiveTime_;
     }
-#endif
 
     void setServerReplyTime(tp t)
     {
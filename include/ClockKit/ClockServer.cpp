@@ -1,6 +1,7 @@
 #include "ClockServer.h"
 
 #include <cmath>
+#include <stdexcept>
 
 #include "ClockPacket.h"
 
@@ -15,6 +16,7 @@ ClockServer::ClockServer(kissnet::endpoint addr_port, Clock& clock)
     , ackData_{map<string, Entry>()}
     , log_(false)
     , tRecalculated_(clock_.getValue())
+    , cancelled_(false)
 {
     kissnet_init();
 }
@@ -22,13 +24,23 @@ ClockServer::ClockServer(kissnet::endpoint addr_port, Clock& clock)
 void ClockServer::run()
 {
     kissnet::udp_socket socket(addr_port_);
-    socket.bind();
+    // Not socket.bind(), whose failure exits the process.
+    if (!socket.bind_local(addr_port_))
+        throw std::runtime_error("bind() failed: bad port number, or another server might be running on that port");
     constexpr auto length = ClockPacket::PACKET_LENGTH;
     ClockPacket::packetbuf buffer;
     if (log_)
         cout << "time                     host    \toffset\tround-trip-time" << endl;
 
-    for (;;) {
+    while (!cancelled_) {
+        // Wait in slices, so that cancel() is noticed without a packet arriving.
+        const auto ready = socket.select(kissnet::fds_read, 100).value;
+        if (ready == kissnet::socket_status::timed_out)
+            continue;
+        if (ready == kissnet::socket_status::errored) {
+            cerr << "ClockServer errored while waiting for a packet.\n";
+            continue;
+        }
         kissnet::addr_collection peer;
         const auto [num_bytes, status] = socket.recv(buffer, 0, &peer);
         const auto now = clock_.getValue();  // Before anything else.
@@ -74,6 +86,16 @@ void ClockServer::run()
     }
 }
 
+void ClockServer::die()
+{
+    kissnet::udp_socket socket(kissnet::endpoint("127.0.0.1", addr_port_.port));
+    ClockPacket::packetbuf buffer;
+    ClockPacket(ClockPacket::KILL).write(buffer);
+    const auto [num_bytes, status] = socket.send(buffer);
+    if (status != kissnet::socket_status::valid)
+        cerr << "ClockServer failed to send a KILL packet: status " << status << "\n";
+}
+
 // Don't shrink this to (addr, packet, now), because
 // that would more tightly couple ClockServer.h to ClockPacket.h.
 void ClockServer::updateEntry(const string& addr, dur offset, dur rtt, tp now)
@@ -1,4 +1,5 @@
 #pragma once
+#include <atomic>
 #include <map>
 #include <string>
 
@@ -37,6 +38,9 @@ class ClockServer {
     // When offsetMax was most recently recalculated.
     tp tRecalculated_;
 
+    // Set by cancel() to make run() return.
+    std::atomic_bool cancelled_;
+
    public:
     // Provide requests for timestamps to clients via a UDP port.
     // To accept connections from clients on *any* local address,
@@ -48,7 +52,22 @@ class ClockServer {
         log_ = log;
     }
 
+    // Serve until cancel() or a KILL packet.  Throws if the port can't be bound.
     void run();
+
+    // Make run() return within 100 ms, even if it hasn't bound its socket yet.
+    // Safe to call from another thread.  clearCancel() before run() to serve again.
+    void cancel()
+    {
+        cancelled_ = true;
+    }
+    void clearCancel()
+    {
+        cancelled_ = false;
+    }
+
+    // Make run() return by sending a KILL packet to this server via localhost.
+    void die();
 };
 
 }  // namespace dex
@@ -2,6 +2,7 @@
 #include "Timestamp.h"
 #include "bridge.h"
 
+#include <algorithm>
 #include <atomic>
 #include <cstdlib>
 #include <memory>
@@ -15,10 +16,6 @@ using std::cout;
 using std::endl;
 #endif
 
-namespace bridge {
-std::atomic_bool stop_clock{false};
-}
-
 namespace dex {
 
 using namespace std::chrono;
@@ -43,7 +40,17 @@ PhaseLockedClock::PhaseLockedClock(Clock &primary, Clock &reference)
     , primaryFrequencyAvg_(1000000.0)
     , phasePanic_(5ms)
     , updatePanic_(5s)
+    , updateInterval_(200ms)
+    , updates_(0)
+    , timeouts_(0)
+    , rttUsec_(0)
+    , lastUpdateUsec_(usecInvalid)
+    , serverTimeUsec_(usecInvalid)
     , updatePrev_(0s)
+    , cancelled_(false)
+    , paused_(false)
+    , resetPending_(false)
+    , everSynchronized_(false)
 {
 }
 
@@ -57,9 +64,25 @@ tp PhaseLockedClock::getValue()
 
 dur PhaseLockedClock::getOffset()
 {
+    // phase_ is written by updatePhase() on the clock thread.
+    Guard guard(mutexPLC);
     return inSync_ ? phase_ : durInvalid;
 }
 
+PhaseLockedClock::Snapshot PhaseLockedClock::getSnapshot()
+{
+    Guard guard(mutexPLC);
+    if (!inSync_)
+        return Snapshot{false, tpInvalid, durInvalid};
+    return Snapshot{true, variableFrequencyClock_.getValue(), phase_};
+}
+
+double PhaseLockedClock::getFrequency()
+{
+    Guard guard(mutexPLC);
+    return variableFrequencyClock_.getFrequency();
+}
+
 void PhaseLockedClock::run(std::atomic_bool &end_clocks)
 {
     // Smear how often we update(), to not overload the server with simultaneous requests.
@@ -67,13 +90,23 @@ void PhaseLockedClock::run(std::atomic_bool &end_clocks)
     std::default_random_engine randNumGen;
     randNumGen.seed(std::random_device{}());
     while (!end_clocks) {
-        update();
-        std::this_thread::sleep_for(200ms * vary(randNumGen));
+        if (!paused_)
+            update();
+        std::this_thread::sleep_for(updateInterval_ * vary(randNumGen));
     }
 }
 
 void PhaseLockedClock::update()
 {
+    if (resetPending_.exchange(false)) {
+        Guard guard(mutexPLC);
+        inSync_ = false;
+        phase_ = phasePrev_ = 0s;
+        variableValue_ = variableValuePrev_ = primaryValue_ = primaryValuePrev_ = tp0;
+        primaryFrequencyAvg_ = 1000000.0;
+        updatePrev_ = tp0;
+        variableFrequencyClock_.setFrequency(1000000.0);
+    }
     if (updatePrev_ == tpInvalid) {
         inSync_ = false;
     }
@@ -85,7 +118,7 @@ void PhaseLockedClock::update()
         else {
             // This logic is brittle, because of how inSync_ is used and set willy-nilly.
             // pv, updatePrev_, and updatePanic_ are not invalid.
-            if (inSync_ && pv > updatePrev_ + updatePanic_) {
+            if (inSync_ && pv > updatePrev_ + updatePanic_.load()) {
                 // The previous update was too long ago.
                 inSync_ = false;
             }
@@ -101,6 +134,7 @@ void PhaseLockedClock::update()
     }
     if (updatePhase()) {
         updatePrev_ = primaryValue();
+        everSynchronized_ = true;
     }
 }
 
@@ -111,7 +145,7 @@ void PhaseLockedClock::setPhasePanic(dur phasePanic)
 
 void PhaseLockedClock::setUpdatePanic(dur usec)
 {
-    if (updatePanic_ != durInvalid)
+    if (updatePanic_.load() != durInvalid)
         updatePanic_ = usec;
 }
 
@@ -120,15 +154,21 @@ bool PhaseLockedClock::updatePhase()
 {
     if (!inSync_)
         return false;
-    mutexPLC.lock();
-    const auto phase = referenceClock_.getPhase(variableFrequencyClock_);
-    const auto variableValue = variableFrequencyClock_.getValue();
-    const auto tmp = primaryValue();  // Not guarded by the mutex.  But read all 3 numbers at once.
-    mutexPLC.unlock();
+    dur phase;
+    tp variableValue;
+    tp tmp;
+    {
+        // Scoped, so that a throwing primary clock doesn't leave the mutex locked.
+        Guard guard(mutexPLC);
+        phase = referenceClock_.getPhase(variableFrequencyClock_);
+        variableValue = variableFrequencyClock_.getValue();
+        tmp = primaryValue();  // Not guarded by the mutex.  But read all 3 numbers at once.
+    }
     if (phase == durInvalid) {
 #ifdef DEBUG
         cout << "lost sync: problem with referenceClock_" << endl;
 #endif
+        ++timeouts_;
         inSync_ = false;
         return false;
     }
@@ -153,10 +193,17 @@ bool PhaseLockedClock::updatePhase()
     primaryValuePrev_ = primaryValue_;
 
     // We've checked that none of these are invalid.
+    // Guarded, for getSnapshot() to see a phase_ matching the vfc.
+    Guard guard(mutexPLC);
     phase_ = phase;
     variableValue_ = variableValue;
     primaryValue_ = tmp;
 
+    ++updates_;
+    rttUsec_ = UsecFromDur(referenceClock_.rtt());
+    lastUpdateUsec_ = UsecFromTp(tmp);
+    serverTimeUsec_ = UsecFromTp(referenceClock_.serverTime());
+
 #ifdef DEBUG
     cout << "phase := " << phase << endl;
 #endif
@@ -173,10 +220,11 @@ bool PhaseLockedClock::updateClock()
         return false;
     }
     // abs() would need -std=c++1z, or UsecFromDur.
-    if (phase_ > phasePanic_ || phase_ < -phasePanic_) {
+    const auto phasePanic = phasePanic_.load();
+    if (phase_ > phasePanic || phase_ < -phasePanic) {
         // The phase is too large.
 #ifdef DEBUG
-        cout << "lost sync: abs(" << phase_ << ") > " << phasePanic_ << endl;
+        cout << "lost sync: abs(" << phase_ << ") > " << phasePanic << endl;
 #endif
         inSync_ = false;
         return false;
@@ -231,14 +279,36 @@ void run1(std::shared_ptr<dex::PhaseLockedClock> clock)
     std::uniform_real_distribution<double> vary(1 / 1.05, 1.05);  // +-5%
     std::default_random_engine randNumGen;
     randNumGen.seed(std::random_device{}());
-    while (!bridge::stop_clock) {
-        clock->update();
-        std::this_thread::sleep_for(200ms * vary(randNumGen));
+    while (!clock->cancelled_) {
+        if (!clock->paused_)
+            clock->update();
+        // Sleep in slices, so that a long interval doesn't delay cancel().
+        const auto wake = steady_clock::now() +
+                          duration_cast<steady_clock::duration>(clock->updateInterval_ * vary(randNumGen));
+        while (!clock->cancelled_ && steady_clock::now() < wake) {
+            std::this_thread::sleep_until(std::min(wake, steady_clock::now() + 200ms));
+        }
     }
 }
 
 void cancel(std::shared_ptr<dex::PhaseLockedClock> clock) {
-    bridge::stop_clock.store(true, std::memory_order::memory_order_seq_cst);
+    clock->cancelled_.store(true, std::memory_order::memory_order_seq_cst);
+}
+
+void clearCancel(std::shared_ptr<dex::PhaseLockedClock> clock) {
+    clock->cancelled_.store(false, std::memory_order::memory_order_seq_cst);
+}
+
+bool isCancelled(const dex::PhaseLockedClock& clock) {
+    return clock.cancelled_.load(std::memory_order::memory_order_seq_cst);
+}
+
+void setPaused(std::shared_ptr<dex::PhaseLockedClock> clock, bool paused) {
+    clock->paused_.store(paused, std::memory_order::memory_order_seq_cst);
+}
+
+bool isPaused(const dex::PhaseLockedClock& clock) {
+    return clock.paused_.load(std::memory_order::memory_order_seq_cst);
 }
 
 
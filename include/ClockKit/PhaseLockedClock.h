@@ -13,6 +13,13 @@ namespace dex {
 // it keeps locked to those of referenceClock_.
 class PhaseLockedClock : public Clock {
    public:
+    // State read at once by getSnapshot().  value and phase are invalid unless inSync.
+    struct Snapshot {
+        bool inSync;
+        tp value;
+        dur phase;
+    };
+
     // Destroy the primary and reference clocks only after destroying this, lest this segfault.
     explicit PhaseLockedClock(Clock& primary, Clock& reference);
 
@@ -21,6 +28,12 @@ class PhaseLockedClock : public Clock {
     PhaseLockedClock(PhaseLockedClock&) = delete;
     PhaseLockedClock& operator=(PhaseLockedClock&) = delete;
 
+    // The clock locked to, usually a ClockClient.
+    Clock& getReferenceClock()
+    {
+        return referenceClock_;
+    }
+
     // Kill the referenceClock_, which is likely a ClockClient, and its ClockServer.
     void die()
     {
@@ -40,15 +53,88 @@ class PhaseLockedClock : public Clock {
     // Phase offset of vfc relative to referenceClock_, i.e., phase_.
     dur getOffset();
 
+    // Frequency of the vfc, in Hz.
+    double getFrequency();
+
+    // Whether an update ever succeeded, unlike isSynchronized() not cleared by losing sync or reset().
+    bool hasEverSynchronized() const
+    {
+        return everSynchronized_;
+    }
+
+    // Discard the phase and frequency estimates, to resynchronize from scratch.
+    // Safe to call while run() is active on another thread, which does the reset.
+    void reset()
+    {
+        resetPending_ = true;
+        inSync_ = false;
+    }
+
+    // isSynchronized(), getValue() and getOffset(), read while holding the mutex.
+    Snapshot getSnapshot();
+
+    // The threshold setters are safe to call while run() is active on another thread.
     void setPhasePanic(dur phasePanic);
+    dur getPhasePanic() const
+    {
+        return phasePanic_.load();
+    }
 
     void setUpdatePanic(dur usec);
+    dur getUpdatePanic() const
+    {
+        return updatePanic_.load();
+    }
+
+    // Counters of updatePhase(), for diagnostics.
+    uint64_t getUpdates() const
+    {
+        return updates_;
+    }
+    uint64_t getTimeouts() const
+    {
+        return timeouts_;
+    }
+    // Round trip time of the last successful update.
+    dur getRtt() const
+    {
+        return DurFromUsec(rttUsec_);
+    }
+    // Time reported by referenceClock_ at the last successful update, tpInvalid if there was none.
+    tp getServerTime() const
+    {
+        return TpFromUsec(serverTimeUsec_);
+    }
+    // Value of primaryClock_ at the last successful update, tpInvalid if there was none.
+    tp getLastUpdate() const
+    {
+        return TpFromUsec(lastUpdateUsec_);
+    }
+    // Time of primaryClock_ since the last successful update, durInvalid if there was none.
+    dur getSyncAge() const
+    {
+        return diff(primaryValue(), getLastUpdate());
+    }
+
+    // How long run() and run1() wait between update()s, smeared by +-5%.
+    void setUpdateInterval(dur interval)
+    {
+        updateInterval_ = interval;
+    }
+    dur getUpdateInterval() const
+    {
+        return updateInterval_;
+    }
 
     // Call update() periodically, until the caller sets the arg to true.
     void run(std::atomic_bool&);
 
     friend void run1(std::shared_ptr<PhaseLockedClock> clock);
     friend void cancel(std::shared_ptr<PhaseLockedClock> clock);
+    friend void clearCancel(std::shared_ptr<PhaseLockedClock> clock);
+    friend bool isCancelled(const PhaseLockedClock& clock);
+    friend void setPaused(std::shared_ptr<PhaseLockedClock> clock, bool paused);
+    friend bool isPaused(const PhaseLockedClock& clock);
 
    protected:
     // Called periodically by run().
@@ -71,7 +157,8 @@ class PhaseLockedClock : public Clock {
         return primaryClock_.getValue();
     }
 
-    bool inSync_;
+    // Atomic, because reset() clears it and isSynchronized() reads it from other threads.
+    std::atomic_bool inSync_;
 
     // Phase between vfc and referenceClock_.
     dur phase_;
@@ -89,15 +176,44 @@ class PhaseLockedClock : public Clock {
     double primaryFrequencyAvg_;
 
     // Thresholds set by setPhasePanic() and setUpdatePanic().
-    dur phasePanic_;
-    dur updatePanic_;
+    // Atomic, because they may be changed while run() reads them on another thread.
+    std::atomic<dur> phasePanic_;
+    std::atomic<dur> updatePanic_;
+
+    // Set by setUpdateInterval().
+    dur updateInterval_;
+
+    // Written by updatePhase(), read from other threads.
+    std::atomic<uint64_t> updates_;
+    std::atomic<uint64_t> timeouts_;
+    std::atomic<int64_t> rttUsec_;
+    std::atomic<int64_t> lastUpdateUsec_;
+    std::atomic<int64_t> serverTimeUsec_;
 
     // When updatePhase() last succeeded.
     tp updatePrev_;
+
+    // Set by cancel() to make run1() return.
+    std::atomic_bool cancelled_;
+
+    // Set by setPaused(), for run() and run1() to skip update()s.
+    std::atomic_bool paused_;
+
+    // Set by reset(), for update() to clear the state on the thread owning it.
+    std::atomic_bool resetPending_;
+
+    // Latched by update() at the first successful updatePhase().
+    std::atomic_bool everSynchronized_;
 };
 
-    // Bridge function that uses a global atomic bool.
+    // Bridge functions that use the clock's cancelled_ flag.
     void run1(std::shared_ptr<PhaseLockedClock> clock);
     void cancel(std::shared_ptr<PhaseLockedClock> clock);
+    // Call before run1(), so that a cancel() racing with the start isn't lost.
+    void clearCancel(std::shared_ptr<PhaseLockedClock> clock);
+    bool isCancelled(const PhaseLockedClock& clock);
+    // While paused, the clock thread keeps running without querying the server.
+    void setPaused(std::shared_ptr<PhaseLockedClock> clock, bool paused);
+    bool isPaused(const PhaseLockedClock& clock);
 
 }  // namespace dex
@@ -32,8 +32,9 @@ tp VariableFrequencyClock::getValue()
 
 void VariableFrequencyClock::setValue(tp t)
 {
-    marker_ = t;                        // possibly tpInvalid
-    markerSrc_ = clockSrc_.getValue();  // possibly tpInvalid
+    const auto src = clockSrc_.getValue();  // Before changing anything, in case it throws.
+    marker_ = t;                            // possibly tpInvalid
+    markerSrc_ = src;                       // possibly tpInvalid
     rolledOver_ = false;
 }
 
@@ -29,6 +29,10 @@ class VariableFrequencyClock : public Clock {
 
     void setValue(tp);
     void setFrequency(double);
+    double getFrequency() const
+    {
+        return frequency_;
+    }
 
    private:
     Clock& clockSrc_;
@@ -11,27 +11,71 @@
 
 namespace bridge {
 
-std::pair<dex::PhaseLockedClock*, dex::ClockClient*> buildClock(ConfigReader config)
+// A Clock calling back into Rust for its value.
+class RustClock : public dex::Clock {
+   public:
+    explicit RustClock(rust::Box<PrimaryClockBox> clock)
+        : clock_(std::move(clock))
+    {
+    }
+
+    dex::tp getValue() override
+    {
+        return dex::TpFromUsec(primaryValue(*clock_));
+    }
+
+   private:
+    rust::Box<PrimaryClockBox> clock_;
+};
+
+std::pair<dex::PhaseLockedClock*, dex::ClockClient*> buildClock(ConfigReader config, dex::Clock& primary)
 {
     auto cli = new dex::ClockClient(kissnet::endpoint(std::string(config.server), config.port));
     cli->setTimeout(config.timeout);
     cli->setAcknowledge(true);
-    auto plc = new dex::PhaseLockedClock(dex::SystemClock::instance(), *cli);
+    if (!config.bindAddress.empty())
+        cli->bindLocal(std::string(config.bindAddress));
+    auto plc = new dex::PhaseLockedClock(primary, *cli);
     plc->setPhasePanic(dex::DurFromUsec(config.phasePanic));
     plc->setUpdatePanic(dex::DurFromUsec(config.updatePanic));
+    plc->setUpdateInterval(dex::DurFromUsec(config.updateInterval));
     return std::make_pair(plc, cli);
 }
 
 std::shared_ptr<dex::PhaseLockedClock> buildPLC(ConfigReader config) {
-    auto [plc, cli] = buildClock(config);
+    auto [plc, cli] = buildClock(config, dex::SystemClock::instance());
     return std::shared_ptr<dex::PhaseLockedClock>(plc);
 }
 
-int64_t getValue(std::shared_ptr<dex::PhaseLockedClock> clock) {
-    auto val = clock->getValue();
+std::shared_ptr<dex::PhaseLockedClock> buildPLCWithPrimary(ConfigReader config, rust::Box<PrimaryClockBox> primary) {
+    auto clock = new RustClock(std::move(primary));
+    auto [plc, cli] = buildClock(config, *clock);
+    // The PLC only references its primary clock, so it has to go with the PLC.
+    return std::shared_ptr<dex::PhaseLockedClock>(plc, [clock](dex::PhaseLockedClock* p) {
+        delete p;
+        delete clock;
+    });
+}
+
+// cxx only hands out const references, the getters only lock the clock's mutex though.
+int64_t getValue(const dex::PhaseLockedClock& clock) {
+    auto val = const_cast<dex::PhaseLockedClock&>(clock).getValue();
     return dex::UsecFromTp(val);
 }
 
+int64_t getOffset(const dex::PhaseLockedClock& clock) {
+    auto val = const_cast<dex::PhaseLockedClock&>(clock).getOffset();
+    return dex::UsecFromDur(val);
+}
+
+double getFrequencyRatio(std::shared_ptr<dex::PhaseLockedClock> clock) {
+    return clock->getFrequency() / 1000000.0;
+}
+
+int64_t getServerTime(std::shared_ptr<dex::PhaseLockedClock> clock) {
+    return dex::UsecFromTp(clock->getServerTime());
+}
+
 
 void setPhasePanic(std::shared_ptr<dex::PhaseLockedClock> clock, int64_t micros) {
     auto val = dex::DurFromUsec(micros);
@@ -43,4 +87,154 @@ void setUpdatePanic(std::shared_ptr<dex::PhaseLockedClock> clock, int64_t micros
     clock->setUpdatePanic(val);
 }
 
+int64_t getPhasePanic(std::shared_ptr<dex::PhaseLockedClock> clock) {
+    return dex::UsecFromDur(clock->getPhasePanic());
+}
+
+int64_t getUpdatePanic(std::shared_ptr<dex::PhaseLockedClock> clock) {
+    return dex::UsecFromDur(clock->getUpdatePanic());
+}
+
+void setTimeout(std::shared_ptr<dex::PhaseLockedClock> clock, int64_t micros) {
+    if (auto client = dynamic_cast<dex::ClockClient*>(&clock->getReferenceClock()))
+        client->setTimeout(micros);
+}
+
+int64_t getTimeout(std::shared_ptr<dex::PhaseLockedClock> clock) {
+    if (auto client = dynamic_cast<dex::ClockClient*>(&clock->getReferenceClock()))
+        return client->getTimeout();
+    return dex::usecInvalid;
+}
+
+uint32_t getLastSequence(std::shared_ptr<dex::PhaseLockedClock> clock) {
+    if (auto client = dynamic_cast<dex::ClockClient*>(&clock->getReferenceClock()))
+        return client->getLastSequence();
+    return dex::ClockClient::noSequence;
+}
+
+SnapshotData getSnapshot(std::shared_ptr<dex::PhaseLockedClock> clock) {
+    const auto snapshot = clock->getSnapshot();
+    return SnapshotData{
+        snapshot.inSync,
+        dex::UsecFromTp(snapshot.value),
+        dex::UsecFromDur(snapshot.phase),
+    };
+}
+
+void reset(std::shared_ptr<dex::PhaseLockedClock> clock) {
+    clock->reset();
+}
+
+int64_t getLastRTT(const dex::PhaseLockedClock& clock) {
+    // The RTT starts out as 0, only report it once there was an update.
+    if (clock.getLastUpdate() == dex::tpInvalid)
+        return dex::usecInvalid;
+    return dex::UsecFromDur(clock.getRtt());
+}
+
+// Reads the primary clock, whose exception from a panicking RustClock
+// terminates here, as in getValue().
+int64_t getSyncAge(const dex::PhaseLockedClock& clock) {
+    return dex::UsecFromDur(clock.getSyncAge());
+}
+
+DiagnosticsData getDiagnostics(std::shared_ptr<dex::PhaseLockedClock> clock) {
+    return DiagnosticsData{
+        dex::UsecFromDur(clock->getRtt()),
+        clock->getUpdates(),
+        clock->getTimeouts(),
+        dex::UsecFromTp(clock->getLastUpdate()),
+    };
+}
+
+std::shared_ptr<dex::ClockClient> buildClient(rust::String server, uint16_t port, uint32_t timeout, rust::String bindAddress) {
+    auto cli = std::make_shared<dex::ClockClient>(kissnet::endpoint(std::string(server), port));
+    cli->setTimeout(timeout);
+    if (!bindAddress.empty())
+        cli->bindLocal(std::string(bindAddress));
+    return cli;
+}
+
+int64_t getClientValue(std::shared_ptr<dex::ClockClient> client) {
+    auto val = client->getValue();
+    return dex::UsecFromTp(val);
+}
+
+static PacketData packetData(const dex::ClockPacket& packet) {
+    return PacketData{
+        static_cast<uint8_t>(packet.getType()),
+        packet.getSeqnum(),
+        dex::UsecFromTp(packet.getClientRequestTime()),
+        dex::UsecFromTp(packet.getServerReplyTime()),
+        dex::UsecFromTp(packet.getClientReceiveTime()),
+    };
+}
+
+PacketData queryClientPacket(std::shared_ptr<dex::ClockClient> client) {
+    return packetData(client->queryPacket());
+}
+
+static void installPacketTrace(dex::ClockClient& client, rust::Box<PacketTraceBox> trace) {
+    // std::function must be copyable, rust::Box isn't.
+    auto shared = std::make_shared<rust::Box<PacketTraceBox>>(std::move(trace));
+    client.setPacketTrace([shared](bool sent, const dex::ClockPacket& packet) {
+        tracePacket(**shared, sent, packetData(packet));
+    });
+}
+
+void setClientPacketTrace(std::shared_ptr<dex::ClockClient> client, rust::Box<PacketTraceBox> trace) {
+    installPacketTrace(*client, std::move(trace));
+}
+
+void setPacketTrace(std::shared_ptr<dex::PhaseLockedClock> clock, rust::Box<PacketTraceBox> trace) {
+    if (auto client = dynamic_cast<dex::ClockClient*>(&clock->getReferenceClock()))
+        installPacketTrace(*client, std::move(trace));
+}
+
+std::shared_ptr<dex::ClockServer> buildServer(uint16_t port) {
+    return std::make_shared<dex::ClockServer>(kissnet::endpoint("0.0.0.0", port), dex::SystemClock::instance());
+}
+
+std::shared_ptr<dex::ClockServer> buildServerWithSource(uint16_t port, rust::Box<PrimaryClockBox> source) {
+    auto clock = new RustClock(std::move(source));
+    auto server = new dex::ClockServer(kissnet::endpoint("0.0.0.0", port), *clock);
+    // Like the PLC's primary clock, the source has to go with the server.
+    return std::shared_ptr<dex::ClockServer>(server, [clock](dex::ClockServer* p) {
+        delete p;
+        delete clock;
+    });
+}
+
+void runServer(std::shared_ptr<dex::ClockServer> server) {
+    server->run();
+}
+
+void cancelServer(std::shared_ptr<dex::ClockServer> server) {
+    server->cancel();
+}
+
+void clearServerCancel(std::shared_ptr<dex::ClockServer> server) {
+    server->clearCancel();
+}
+
+std::shared_ptr<dex::VariableFrequencyClock> buildVariableClock() {
+    return std::make_shared<dex::VariableFrequencyClock>(dex::SystemClock::instance());
+}
+
+int64_t getVariableValue(std::shared_ptr<dex::VariableFrequencyClock> clock) {
+    return dex::UsecFromTp(clock->getValue());
+}
+
+void setVariableFrequency(std::shared_ptr<dex::VariableFrequencyClock> clock, double frequency) {
+    clock->setFrequency(frequency);
+}
+
+double getVariableFrequency(std::shared_ptr<dex::VariableFrequencyClock> clock) {
+    return clock->getFrequency();
+}
+
+int64_t getSystemValue() {
+    return dex::UsecFromTp(dex::SystemClock::instance().getValue());
+}
+
 } // ns bridge
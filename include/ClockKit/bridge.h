@@ -1,16 +1,91 @@
 #pragma once
+#include "ClockClient.h"
+#include "ClockServer.h"
 #include "PhaseLockedClock.h"
+#include "VariableFrequencyClock.h"
+
+#include "rust/cxx.h"
 
 namespace bridge {
 
 struct ConfigReader;
+struct PacketData;
+struct DiagnosticsData;
+struct SnapshotData;
+struct PrimaryClockBox;
+struct PacketTraceBox;
 
 std::shared_ptr<dex::PhaseLockedClock> buildPLC(ConfigReader config);
 
-int64_t getValue(std::shared_ptr<dex::PhaseLockedClock> clock);
+// Use a clock implemented in Rust as the primary clock instead of SystemClock.
+std::shared_ptr<dex::PhaseLockedClock> buildPLCWithPrimary(ConfigReader config, rust::Box<PrimaryClockBox> primary);
+
+// Borrow the clock on the hot paths, copying the shared_ptr costs two atomic refcount updates.
+int64_t getValue(const dex::PhaseLockedClock& clock);
+
+int64_t getOffset(const dex::PhaseLockedClock& clock);
+
+double getFrequencyRatio(std::shared_ptr<dex::PhaseLockedClock> clock);
+
+int64_t getServerTime(std::shared_ptr<dex::PhaseLockedClock> clock);
 
 void setPhasePanic(std::shared_ptr<dex::PhaseLockedClock> clock, int64_t micros);
 
 void setUpdatePanic(std::shared_ptr<dex::PhaseLockedClock> clock, int64_t micros);
 
+int64_t getPhasePanic(std::shared_ptr<dex::PhaseLockedClock> clock);
+
+int64_t getUpdatePanic(std::shared_ptr<dex::PhaseLockedClock> clock);
+
+// The request timeout of the PLC's client, usecInvalid if the reference clock isn't one.
+void setTimeout(std::shared_ptr<dex::PhaseLockedClock> clock, int64_t micros);
+
+int64_t getTimeout(std::shared_ptr<dex::PhaseLockedClock> clock);
+
+uint32_t getLastSequence(std::shared_ptr<dex::PhaseLockedClock> clock);
+
+SnapshotData getSnapshot(std::shared_ptr<dex::PhaseLockedClock> clock);
+
+void reset(std::shared_ptr<dex::PhaseLockedClock> clock);
+
+int64_t getLastRTT(const dex::PhaseLockedClock& clock);
+
+int64_t getSyncAge(const dex::PhaseLockedClock& clock);
+
+DiagnosticsData getDiagnostics(std::shared_ptr<dex::PhaseLockedClock> clock);
+
+std::shared_ptr<dex::ClockClient> buildClient(rust::String server, uint16_t port, uint32_t timeout, rust::String bindAddress);
+
+int64_t getClientValue(std::shared_ptr<dex::ClockClient> client);
+
+PacketData queryClientPacket(std::shared_ptr<dex::ClockClient> client);
+
+// Pass every packet of the client to tracePacket() in Rust.
+void setClientPacketTrace(std::shared_ptr<dex::ClockClient> client, rust::Box<PacketTraceBox> trace);
+
+// Like setClientPacketTrace() for the PLC's client, does nothing if the reference clock isn't one.
+void setPacketTrace(std::shared_ptr<dex::PhaseLockedClock> clock, rust::Box<PacketTraceBox> trace);
+
+std::shared_ptr<dex::ClockServer> buildServer(uint16_t port);
+
+// A server handing out the time of a Rust clock instead of the system clock.
+std::shared_ptr<dex::ClockServer> buildServerWithSource(uint16_t port, rust::Box<PrimaryClockBox> source);
+
+void runServer(std::shared_ptr<dex::ClockServer> server);
+
+void cancelServer(std::shared_ptr<dex::ClockServer> server);
+
+// Call before runServer(), so that a cancelServer() racing with the start isn't lost.
+void clearServerCancel(std::shared_ptr<dex::ClockServer> server);
+
+std::shared_ptr<dex::VariableFrequencyClock> buildVariableClock();
+
+int64_t getVariableValue(std::shared_ptr<dex::VariableFrequencyClock> clock);
+
+void setVariableFrequency(std::shared_ptr<dex::VariableFrequencyClock> clock, double frequency);
+
+double getVariableFrequency(std::shared_ptr<dex::VariableFrequencyClock> clock);
+
+int64_t getSystemValue();
+
 } // ns bridge
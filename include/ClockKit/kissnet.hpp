@@ -971,6 +971,23 @@ namespace kissnet
 			}
 		}
 
+		///Bind socket locally to the numeric address of local, e.g. to choose the interface to send from. clockkit addition.
+		/// \return false if local isn't a valid address of the socket's family, or binding failed
+		bool bind_local(const endpoint& local)
+		{
+			addrinfo hints	   = {};
+			hints.ai_family	   = socket_addrinfo->ai_family;
+			hints.ai_socktype  = socket_addrinfo->ai_socktype;
+			hints.ai_protocol  = socket_addrinfo->ai_protocol;
+			hints.ai_flags	   = AI_PASSIVE | AI_NUMERICHOST;
+			addrinfo* results = nullptr;
+			if (getaddrinfo(local.address.c_str(), std::to_string(local.port).c_str(), &hints, &results) != 0)
+				return false;
+			const bool bound = syscall_bind(sock, results->ai_addr, socklen_t(results->ai_addrlen)) != SOCKET_ERROR;
+			freeaddrinfo(results);
+			return bound;
+		}
+
 		///(For TCP) connect to the endpoint as client
 		socket_status connect(int64_t timeout = 0)
 		{
@@ -0,0 +1,123 @@
+//! Async interface to the [`PhaseLockedClock`] for tokio, requires the `tokio` feature.
+
+use crate::{ffi, run_clock_keeping_error, Config, Error, PhaseLockedClock, MONITOR_INTERVAL};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+use tokio::{sync::watch, task::JoinHandle};
+
+/// A [`PhaseLockedClock`] for use within a tokio runtime.
+///
+/// The PLC runs on a blocking task and every FFI call which might block is dispatched off the
+/// async executor. The synchronization state is tracked in a [`watch`] channel.
+///
+/// Example:
+/// ```
+/// # use clockkit::{self, AsyncPhaseLockedClock};
+/// # use std::time::Duration;
+/// let server = clockkit::Server::new(4450);
+/// server.start();
+///
+/// let rt = tokio::runtime::Builder::new_current_thread()
+///     .enable_time()
+///     .build()
+///     .unwrap();
+/// rt.block_on(async {
+///     let clock = AsyncPhaseLockedClock::new(clockkit::Config::default().port(4450));
+///     tokio::time::timeout(Duration::from_secs(5), clock.wait_synchronized())
+///         .await
+///         .expect("Clock did not synchronize")
+///         .unwrap();
+///     clock.value().await.expect("Failed to get value");
+/// });
+/// ```
+pub struct AsyncPhaseLockedClock {
+    clock: Arc<PhaseLockedClock>,
+    synchronized: watch::Receiver<bool>,
+    runner: JoinHandle<()>,
+    watcher: JoinHandle<()>,
+}
+
+impl AsyncPhaseLockedClock {
+    /// Build the clock from `config` and start it.
+    ///
+    /// Panics if called outside of a tokio runtime.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub fn new(config: Config) -> Self {
+        let clock = Arc::new(config.build_clock());
+
+        let plc = clock.ptr.clone();
+        ffi::clearCancel(plc.clone());
+        let last_error = clock.last_error.clone();
+        let runner = tokio::task::spawn_blocking(move || run_clock_keeping_error(plc, &last_error));
+
+        let (tx, synchronized) = watch::channel(false);
+        let plc = clock.ptr.clone();
+        let watcher = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(MONITOR_INTERVAL);
+            loop {
+                interval.tick().await;
+                // isSynchronized only reads a flag and does not block.
+                let now = plc.isSynchronized();
+                tx.send_if_modified(|state| std::mem::replace(state, now) != now);
+            }
+        });
+
+        Self {
+            clock,
+            synchronized,
+            runner,
+            watcher,
+        }
+    }
+
+    /// Get the current timestamp, see [`PhaseLockedClock::get_value`].
+    #[cfg(feature = "chrono")]
+    pub async fn value(&self) -> Result<DateTime<Utc>, Error> {
+        let clock = self.clock.clone();
+        tokio::task::spawn_blocking(move || clock.get_value())
+            .await
+            .expect("getValue task panicked")
+    }
+
+    /// Check whether the PLC is synchronized.
+    pub fn is_synchronized(&self) -> bool {
+        *self.synchronized.borrow()
+    }
+
+    /// Get the error that ended the PLC's blocking task, see [`PhaseLockedClock::last_error`].
+    pub fn last_error(&self) -> Option<Error> {
+        self.clock.last_error()
+    }
+
+    /// Wait until the PLC is synchronized.
+    ///
+    /// This does not time out by itself, wrap it in [`tokio::time::timeout`] to bound it.
+    pub async fn wait_synchronized(&self) -> Result<(), Error> {
+        let mut synchronized = self.synchronized.clone();
+        synchronized
+            .wait_for(|s| *s)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::OutOfSync)
+    }
+
+    /// Stop the PLC and wait for its blocking task to finish.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub async fn stop(mut self) {
+        self.watcher.abort();
+        ffi::cancel(self.clock.ptr.clone());
+        if (&mut self.runner).await.is_err() {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("PhaseLockedClock task panicked");
+        }
+    }
+}
+
+impl Drop for AsyncPhaseLockedClock {
+    /// Cancels the PLC without waiting for the blocking task, use [`Self::stop`] to wait.
+    fn drop(&mut self) {
+        self.watcher.abort();
+        ffi::cancel(self.clock.ptr.clone());
+    }
+}
@@ -0,0 +1,70 @@
+//! Command line parsing shared by clockkit clients.
+
+use crate::{Config, Error};
+
+/// Build a [`Config`] from command line arguments, without the program name.
+///
+/// Accepts an optional config file, read with [`Config::from_config_file`], and the options
+/// `--server <host>`, `--port <port>` and `--endpoint <host:port>`, which override the file's
+/// settings. Unknown options and missing or malformed values fail with
+/// [`Error::ConfigInvalid`].
+///
+/// Example:
+/// ```
+/// # use clockkit::{parse_cli_config, Error};
+/// let args: Vec<String> = ["--server", "10.10.10.20", "--port", "1234"]
+///     .map(String::from)
+///     .into();
+/// let config = parse_cli_config(&args).unwrap();
+/// assert_eq!((config.server.as_str(), config.port), ("10.10.10.20", 1234));
+///
+/// let args = ["--endpoint".to_string(), "[::1]:4445".to_string()];
+/// let config = parse_cli_config(&args).unwrap();
+/// assert_eq!((config.server.as_str(), config.port), ("::1", 4445));
+///
+/// let err = parse_cli_config(&["--port".to_string()]).unwrap_err();
+/// assert_eq!(err.to_string(), "Invalid config value for '--port': missing value");
+/// assert!(matches!(
+///     parse_cli_config(&["--verbose".to_string()]),
+///     Err(Error::ConfigInvalid { .. })
+/// ));
+/// ```
+///
+/// A CLI would pass `std::env::args().skip(1).collect::<Vec<_>>()`.
+pub fn parse_cli_config(args: &[String]) -> Result<Config, Error> {
+    let invalid = |key: &str, reason| Error::ConfigInvalid {
+        key: key.to_string(),
+        reason,
+    };
+    let mut file = None;
+    let mut overrides = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg.starts_with("--") {
+            let value = args.next().ok_or_else(|| invalid(arg, "missing value"))?;
+            overrides.push((arg.as_str(), value.as_str()));
+        } else if file.replace(arg).is_some() {
+            return Err(invalid(arg, "only one config file may be given"));
+        }
+    }
+
+    let mut config = match file {
+        Some(path) => Config::from_config_file(path)?,
+        None => Config::default(),
+    };
+    for (option, value) in overrides {
+        config = match option {
+            "--server" => config.server(value),
+            "--port" => config.port(
+                value
+                    .parse()
+                    .map_err(|_| invalid(option, "must be a port number"))?,
+            ),
+            "--endpoint" => config
+                .endpoint(value)
+                .map_err(|_| invalid(option, "expected host:port or [ipv6]:port"))?,
+            _ => return Err(invalid(option, "unknown option")),
+        };
+    }
+    Ok(config)
+}
@@ -0,0 +1,106 @@
+//! A common interface for synchronized time sources.
+
+use crate::{Error, Timestamp};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+#[cfg(feature = "mock")]
+use std::{collections::VecDeque, sync::Mutex};
+
+/// Something giving a synchronized timestamp, e.g. a [`PhaseLockedClock`](crate::PhaseLockedClock).
+///
+/// Implemented by [`PhaseLockedClock`](crate::PhaseLockedClock) and its
+/// [`ClockHandle`](crate::ClockHandle), by [`ClockGroup`](crate::ClockGroup), by
+/// [`ClockClient`](crate::ClockClient) and by the local [`SystemClock`](crate::SystemClock),
+/// which can stand in for a synchronized clock during development. Code generic over `Clock` can
+/// also be tested with a `MockClock`, see the `mock` feature.
+///
+/// Example:
+/// ```
+/// # use clockkit::{Clock, Error, SystemClock};
+/// fn log_event(clock: &impl Clock, event: &str) -> Result<String, Error> {
+///     Ok(format!("{} {event}", clock.get_timestamp()?.as_micros()))
+/// }
+///
+/// assert!(log_event(&SystemClock, "start").unwrap().ends_with(" start"));
+/// ```
+pub trait Clock {
+    /// Get the current timestamp, failing with [`Error::OutOfSync`] if not synchronized.
+    fn get_timestamp(&self) -> Result<Timestamp, Error>;
+
+    /// Check whether the clock is synchronized.
+    fn is_synchronized(&self) -> bool;
+
+    /// Get the current timestamp as `DateTime`, requires the `chrono` feature.
+    #[cfg(feature = "chrono")]
+    fn get_value(&self) -> Result<DateTime<Utc>, Error> {
+        self.get_timestamp()?.to_datetime()
+    }
+}
+
+/// A [`Clock`] returning scripted values, without a server or threads. Requires the `mock`
+/// feature.
+///
+/// Every [`get_timestamp`](Clock::get_timestamp) takes the next value, `None` stands for a
+/// reading while out of sync. Once all values are used up the clock is out of sync.
+///
+/// Example:
+/// ```
+/// # use clockkit::{Clock, Error, MockClock, Timestamp};
+/// fn elapsed(clock: &impl Clock) -> Result<i64, Error> {
+///     let start = clock.get_timestamp()?;
+///     let end = clock.get_timestamp()?;
+///     Ok(end.as_micros() - start.as_micros())
+/// }
+///
+/// let clock = MockClock::new([
+///     Some(Timestamp::from_micros(1_000)),
+///     Some(Timestamp::from_micros(1_250)),
+///     None,
+/// ]);
+/// assert!(clock.is_synchronized());
+/// assert_eq!(elapsed(&clock).unwrap(), 250);
+/// assert!(!clock.is_synchronized());
+/// assert!(matches!(clock.get_timestamp(), Err(Error::OutOfSync)));
+/// assert!(matches!(clock.get_timestamp(), Err(Error::OutOfSync)));
+/// ```
+#[cfg(feature = "mock")]
+#[derive(Debug, Default)]
+pub struct MockClock {
+    values: Mutex<VecDeque<Option<Timestamp>>>,
+}
+
+#[cfg(feature = "mock")]
+impl MockClock {
+    pub fn new(values: impl IntoIterator<Item = Option<Timestamp>>) -> Self {
+        Self {
+            values: Mutex::new(values.into_iter().collect()),
+        }
+    }
+
+    /// Append a value to the script.
+    pub fn push(&self, value: Option<Timestamp>) {
+        match self.values.lock() {
+            Ok(mut values) => values.push_back(value),
+            Err(poisoned) => poisoned.into_inner().push_back(value),
+        }
+    }
+}
+
+#[cfg(feature = "mock")]
+impl Clock for MockClock {
+    fn get_timestamp(&self) -> Result<Timestamp, Error> {
+        let next = match self.values.lock() {
+            Ok(mut values) => values.pop_front(),
+            Err(poisoned) => poisoned.into_inner().pop_front(),
+        };
+        next.flatten().ok_or(Error::OutOfSync)
+    }
+
+    /// Whether the next value is a valid one.
+    fn is_synchronized(&self) -> bool {
+        match self.values.lock() {
+            Ok(values) => matches!(values.front(), Some(Some(_))),
+            Err(poisoned) => matches!(poisoned.into_inner().front(), Some(Some(_))),
+        }
+    }
+}
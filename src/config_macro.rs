@@ -0,0 +1,127 @@
+//! Support for the [`config!`](crate::config!) macro.
+
+use crate::Config;
+
+/// The settings of a [`config!`](crate::config!), checked while compiling. Not public API.
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug)]
+pub struct ConstConfig {
+    pub server: &'static str,
+    pub port: u16,
+    pub timeout: u32,
+    pub phase_panic: u32,
+    pub update_panic: u32,
+    pub update_interval: u32,
+}
+
+impl ConstConfig {
+    /// The values of [`Config::default`].
+    pub const DEFAULT: Self = Self {
+        server: "127.0.0.1",
+        port: 4444,
+        timeout: 1000,
+        phase_panic: 5000,
+        update_panic: 5000000,
+        update_interval: 200000,
+    };
+
+    pub const fn server(self, server: &'static str) -> Self {
+        Self { server, ..self }
+    }
+
+    pub const fn port(self, port: u16) -> Self {
+        Self { port, ..self }
+    }
+
+    pub const fn timeout(self, timeout: u32) -> Self {
+        Self { timeout, ..self }
+    }
+
+    pub const fn phase_panic(self, phase_panic: u32) -> Self {
+        Self {
+            phase_panic,
+            ..self
+        }
+    }
+
+    pub const fn update_panic(self, update_panic: u32) -> Self {
+        Self {
+            update_panic,
+            ..self
+        }
+    }
+
+    pub const fn update_interval(self, update_interval: u32) -> Self {
+        Self {
+            update_interval,
+            ..self
+        }
+    }
+
+    /// The checks of [`Config::validate`] that apply, as a compile error when evaluated in a
+    /// const.
+    pub const fn check(&self) {
+        assert!(!self.server.is_empty(), "config!: server must not be empty");
+        assert!(self.port != 0, "config!: port must not be 0");
+        assert!(self.timeout != 0, "config!: timeout must not be 0");
+        assert!(
+            self.update_interval != 0,
+            "config!: update_interval must not be 0"
+        );
+        assert!(
+            self.update_panic > self.update_interval,
+            "config!: update_panic must be larger than update_interval"
+        );
+        assert!(
+            self.update_panic > self.timeout,
+            "config!: update_panic must be larger than timeout"
+        );
+    }
+
+    pub fn build(self) -> Config {
+        Config::default()
+            .server(self.server)
+            .port(self.port)
+            .timeout(self.timeout)
+            .phase_panic(self.phase_panic)
+            .update_panic(self.update_panic)
+            .update_interval(self.update_interval)
+    }
+}
+
+/// Build a [`Config`] from settings known at compile time, which are checked while compiling.
+///
+/// The keys are `server`, `port`, `timeout`, `phase_panic`, `update_panic` and `update_interval`,
+/// as for the builder methods of [`Config`], the others keep their defaults. The values must be
+/// constants, and a config which would fail [`Config::validate`] for an empty server, port 0, a
+/// zero timeout or update interval, or an update panic not larger than the update interval or the
+/// timeout, is a compile error. Nothing is parsed at runtime.
+///
+/// Example:
+/// ```
+/// # use clockkit::Config;
+/// const PORT: u16 = 4444;
+/// let config = clockkit::config! {
+///     server: "10.0.0.1",
+///     port: PORT,
+///     phase_panic: 5000,
+/// };
+/// assert_eq!(config, Config::default().server("10.0.0.1").port(4444).phase_panic(5000));
+/// assert!(config.validate().is_ok());
+/// ```
+///
+/// An invalid setting doesn't compile:
+/// ```compile_fail
+/// let config = clockkit::config! { server: "10.0.0.1", port: 0 };
+/// ```
+/// ```compile_fail
+/// let config = clockkit::config! { update_interval: 200000, update_panic: 100000 };
+/// ```
+#[macro_export]
+macro_rules! config {
+    ($($key:ident: $value:expr),* $(,)?) => {{
+        const CONFIG: $crate::__ConstConfig = $crate::__ConstConfig::DEFAULT $(.$key($value))*;
+        const _: () = CONFIG.check();
+        CONFIG.build()
+    }};
+}
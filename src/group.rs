@@ -0,0 +1,91 @@
+//! Redundant clocks locked to different servers.
+
+use crate::{Clock, Error, PhaseLockedClock, Timestamp};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
+/// Several [`PhaseLockedClock`]s, e.g. locked to redundant servers, read as one.
+///
+/// The value is taken from the synchronized clock with the smallest absolute offset to its
+/// server at that instant. As a [`Clock`] the group is synchronized while any of its clocks is.
+///
+/// Example:
+/// ```
+/// # use clockkit::{self, ClockGroup, Error};
+/// # use std::time::Duration;
+/// let server = clockkit::Server::new(4477);
+/// server.start();
+///
+/// let group = ClockGroup::new(vec![
+///     clockkit::Config::default().port(4477).build_clock(),
+///     // Never started, so it stays out of sync.
+///     clockkit::Config::default().port(4453).build_clock(),
+/// ]);
+/// assert!(matches!(group.best_timestamp(), Err(Error::OutOfSync)));
+///
+/// let primary = &group.clocks()[0];
+/// primary.start();
+/// primary.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+/// assert!(group.any_synchronized());
+/// assert!(!group.all_synchronized());
+/// group.best_timestamp().unwrap();
+/// ```
+pub struct ClockGroup {
+    clocks: Vec<PhaseLockedClock>,
+}
+
+impl ClockGroup {
+    pub fn new(clocks: Vec<PhaseLockedClock>) -> Self {
+        Self { clocks }
+    }
+
+    pub fn clocks(&self) -> &[PhaseLockedClock] {
+        &self.clocks
+    }
+
+    /// Hand out the clocks, e.g. to shut them down one by one.
+    pub fn into_clocks(self) -> Vec<PhaseLockedClock> {
+        self.clocks
+    }
+
+    /// Check whether every clock is synchronized, `true` for an empty group.
+    pub fn all_synchronized(&self) -> bool {
+        self.clocks.iter().all(PhaseLockedClock::is_synchronized)
+    }
+
+    /// Check whether at least one clock is synchronized.
+    pub fn any_synchronized(&self) -> bool {
+        self.clocks.iter().any(PhaseLockedClock::is_synchronized)
+    }
+
+    /// Get the timestamp of the synchronized clock with the smallest absolute offset.
+    ///
+    /// Each clock's value and offset are read together, see [`PhaseLockedClock::snapshot`].
+    /// Fails with [`Error::OutOfSync`] if no clock is synchronized.
+    pub fn best_timestamp(&self) -> Result<Timestamp, Error> {
+        self.clocks
+            .iter()
+            .map(PhaseLockedClock::snapshot)
+            .filter_map(|snapshot| Some((snapshot.offset?, snapshot.value.ok()?)))
+            .min_by_key(|(offset, _)| offset.unsigned_abs())
+            .map(|(_, value)| value)
+            .ok_or(Error::OutOfSync)
+    }
+
+    /// Get the value like [`Self::best_timestamp`] as `DateTime`, requires the `chrono`
+    /// feature.
+    #[cfg(feature = "chrono")]
+    pub fn best_value(&self) -> Result<DateTime<Utc>, Error> {
+        self.best_timestamp()?.to_datetime()
+    }
+}
+
+impl Clock for ClockGroup {
+    fn get_timestamp(&self) -> Result<Timestamp, Error> {
+        self.best_timestamp()
+    }
+
+    fn is_synchronized(&self) -> bool {
+        self.any_synchronized()
+    }
+}
@@ -0,0 +1,69 @@
+//! The liveness file of [`Config::heartbeat_file`](crate::Config::heartbeat_file).
+
+use crate::USEC_INVALID;
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// Rewrites the heartbeat file with the clock's state, at most once per interval.
+#[derive(Clone, Debug)]
+pub(crate) struct Heartbeat {
+    path: PathBuf,
+    interval: Duration,
+    last: Option<Instant>,
+    /// Whether the last write failed, so that a failure is only logged once.
+    failing: bool,
+}
+
+impl Heartbeat {
+    pub(crate) fn new(path: PathBuf, interval: Duration) -> Self {
+        Self {
+            path,
+            interval,
+            last: None,
+            failing: false,
+        }
+    }
+
+    /// Write the state unless that was done less than an interval ago.
+    pub(crate) fn beat(&mut self, synchronized: bool, value: i64, offset: i64) {
+        let now = Instant::now();
+        if self
+            .last
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return;
+        }
+        self.last = Some(now);
+        let result = self.write(synchronized, value, offset);
+        #[cfg(feature = "tracing")]
+        match (&result, self.failing) {
+            (Err(e), false) => {
+                tracing::warn!(path = ?self.path, error = %e, "Failed to write heartbeat file")
+            }
+            (Ok(()), true) => tracing::info!(path = ?self.path, "Heartbeat file written again"),
+            _ => {}
+        }
+        self.failing = result.is_err();
+    }
+
+    /// Replace the file, via a temporary one next to it so that readers never see a partial one.
+    fn write(&self, synchronized: bool, value: i64, offset: i64) -> io::Result<()> {
+        let mut tmp = OsString::from(self.path.as_os_str());
+        tmp.push(".tmp");
+        let mut file = fs::File::create(&tmp)?;
+        writeln!(file, "synchronized:{synchronized}")?;
+        if synchronized && value != USEC_INVALID {
+            writeln!(file, "value:{value}")?;
+        }
+        if synchronized && offset != USEC_INVALID {
+            writeln!(file, "offset:{offset}")?;
+        }
+        drop(file);
+        fs::rename(&tmp, &self.path)
+    }
+}
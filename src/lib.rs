@@ -1,25 +1,52 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
+//! Accurate distributed timestamps.
+//!
+//! Bindings to [clockkit](https://github.com/camilleg/clockkit).
+//!
+//! Clockkit provides timestamps to distributed networked PCs
+//! with guaranteed bounds on latency and jitter, typically under 10 microseconds,
+//! as described in the conference paper
+//! [Synchronous data collection from diverse hardware](https://github.com/camilleg/clockkit/blob/main/dsceu04.pdf).
+//!
+//! It runs on Linux, Windows, and Raspi, and needs neither extra hardware nor elevated privileges.
+//!
+//! It can measure a system's realtime behavior, by providing a common time reference for events recorded by different sensors
+//! (audio, video, gamepad, GPS, SMS, MIDI, biometrics), and for triggering outputs (audio, video, LEDs, servos, motion bases).
+//!
+//! Originally created for a full-motion
+//! [driving simulator](https://web.archive.org/web/20170517201424/http://www.isl.uiuc.edu/Labs/Driving%20Simulator/Driving%20Simulator.html)
+//! with eye tracking and a quickly churning set of other sensors and outputs, for over a decade.
+//!
+//! # Features
+//! - `chrono` (default): timestamps as [`chrono::DateTime`], e.g. `PhaseLockedClock::get_value`.
+//!   Without it timestamps are available as raw μs or [`std::time::SystemTime`].
+//! - `tracing`: instrumentation and events on synchronization changes via `tracing`.
+//! - `serde`: `Serialize` and `Deserialize` for [`Config`], `Serialize` for [`Diagnostics`],
+//!   [`Metrics`] and [`Snapshot`].
+//! - `tokio`: `AsyncPhaseLockedClock` for use within a tokio runtime.
+//! - `mock`: `MockClock`, a [`Clock`] with scripted values for testing without a server.
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
 use cxx::{self, SharedPtr};
-///! Accurate distributed timestamps.
-///!
-///! Bindings to [clockkit](https://github.com/camilleg/clockkit).
-///!
-///! Clockkit provides timestamps to distributed networked PCs
-///! with guaranteed bounds on latency and jitter, typically under 10 microseconds,
-///! as described in the conference paper
-///! [Synchronous data collection from diverse hardware](https://github.com/camilleg/clockkit/blob/main/dsceu04.pdf).
-///!
-///! It runs on Linux, Windows, and Raspi, and needs neither extra hardware nor elevated privileges.
-///!
-///! It can measure a system's realtime behavior, by providing a common time reference for events recorded by different sensors
-///! (audio, video, gamepad, GPS, SMS, MIDI, biometrics), and for triggering outputs (audio, video, LEDs, servos, motion bases).
-///!
-///! Originally created for a full-motion
-///! [driving simulator](https://web.archive.org/web/20170517201424/http://www.isl.uiuc.edu/Labs/Driving%20Simulator/Driving%20Simulator.html)
-///! with eye tracking and a quickly churning set of other sensors and outputs, for over a decade.
-use std::{fmt::Debug, path::Path, sync::Mutex, thread::JoinHandle, time::Duration};
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fmt::Debug,
+    io::Read,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs, UdpSocket},
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use thiserror::Error;
 
+use std::sync::{
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    mpsc, Arc,
+};
+
 /// Things that can go wrong.
 #[derive(Error, Debug)]
 pub enum Error {
@@ -32,41 +59,188 @@ pub enum Error {
     /// The internal representation overflowed.
     #[error("Overflow")]
     Overflow,
+    /// A mutex was poisoned by a panicking thread.
+    #[error("Mutex poisoned")]
+    LockPoisoned,
+    /// The clock's thread panicked.
+    #[error("Clock thread panicked")]
+    ThreadPanicked,
+    /// The clock's thread ended with an exception from clockkit, e.g. a failing socket, or a
+    /// panic of a custom primary clock.
+    #[error("Clock thread failed: {0}")]
+    ClockThread(String),
+    /// The server's name could not be resolved.
+    #[error("Could not resolve server '{0}'")]
+    Resolve(String),
+    /// No socket to the server could be set up, e.g. because it is unreachable from the bind
+    /// address.
+    #[error("Could not connect to server: {0}")]
+    Connect(String),
     /// Invalid value.
     #[error("Invalid value: {0}")]
     Invalid(i64),
-    /// Could not read config file.
-    #[error("Could not read config file")]
-    ConfigRead(#[from] std::io::Error),
-    /// Invalid configuration value.
-    #[error("Invalid configuration value")]
-    ConfigValue,
-    /// Invalid configuration key.
-    #[error("Invalid configuration key")]
-    ConfigKey(String),
+    /// Could not read the config file at `path`, `None` if it was read from elsewhere, e.g. by
+    /// [`Config::from_reader`].
+    #[error(
+        "Could not read config file{}: {source}",
+        .path.as_ref().map(|p| format!(" '{}'", p.display())).unwrap_or_default()
+    )]
+    ConfigRead {
+        path: Option<PathBuf>,
+        #[source]
+        source: std::io::Error,
+    },
+    /// Could not write config file.
+    #[error("Could not write config file")]
+    ConfigWrite(#[source] std::io::Error),
+    /// Invalid configuration value for `key` on the 1-based `line`.
+    #[error("Invalid value for '{key}' on line {line}")]
+    ConfigValue { line: usize, key: String },
+    /// Invalid configuration key on the 1-based `line`.
+    #[error("Invalid key '{key}' on line {line}")]
+    ConfigKey { line: usize, key: String },
+    /// Configuration value for `key` can't work, e.g. port 0.
+    #[error("Invalid config value for '{key}': {reason}")]
+    ConfigInvalid { key: String, reason: &'static str },
+}
+
+impl From<std::io::Error> for Error {
+    /// A [`Error::ConfigRead`] without a path.
+    fn from(source: std::io::Error) -> Self {
+        Self::ConfigRead { path: None, source }
+    }
 }
 
+mod cli;
+mod clock;
+mod config_macro;
+mod group;
+mod heartbeat;
+mod timestamp;
+pub use cli::parse_cli_config;
+pub use clock::Clock;
+#[cfg(feature = "mock")]
+pub use clock::MockClock;
+#[doc(hidden)]
+pub use config_macro::ConstConfig as __ConstConfig;
+pub use group::ClockGroup;
+pub use timestamp::Timestamp;
+
+use heartbeat::Heartbeat;
+
+#[cfg(feature = "tokio")]
+mod async_clock;
+#[cfg(feature = "tokio")]
+pub use async_clock::AsyncPhaseLockedClock;
+
+#[cfg(feature = "prometheus")]
+mod prometheus_metrics;
+#[cfg(feature = "prometheus")]
+pub use prometheus_metrics::register_metrics;
+
+#[cfg(feature = "packet_trace")]
+pub mod packet_trace;
+#[cfg(all(feature = "packet_trace", feature = "tracing"))]
+pub use packet_trace::log_packet;
+#[cfg(feature = "packet_trace")]
+pub use packet_trace::{packet_writer, PacketDirection};
+
 // Obviously invalid values.  9223372036854775807 usec, or 293,000 years.
 const USEC_INVALID: i64 = i64::MAX;
 
 #[cxx::bridge]
+// The code generated by cxx for `Box` arguments trips newer lints.
+#[allow(unused_must_use)]
 mod ffi {
 
     /// Example:
     /// ```
     /// use clockkit;
     /// let clock = clockkit::Config::default()
-    ///     .server("10.10.10.20".to_string())
+    ///     .server("10.10.10.20")
     ///     .port(1234)
     ///     .build_clock();
+    /// ```
+    ///
+    /// Configs can be cloned, compared and printed:
+    /// ```
+    /// # use clockkit;
+    /// let base = clockkit::Config::default().server("10.10.10.20");
+    /// let other = base.clone();
+    /// assert_eq!(base, other);
+    /// assert!(format!("{:?}", other).contains("10.10.10.20"));
+    /// assert_ne!(base, other.port(1234));
+    /// ```
     #[namespace = "bridge"]
-    #[derive(Debug)]
+    #[derive(Clone, Debug, PartialEq, Eq)]
     struct ConfigReader {
         server: String,
         port: u16,
         timeout: u32,
         phasePanic: u32,
         updatePanic: u32,
+        /// Time between two updates from the server in μs.
+        updateInterval: u32,
+        /// Endpoints tried in order if `server:port` does not answer when building a clock.
+        fallbacks: Vec<Endpoint>,
+        /// Local IP address to send from, empty to leave the choice to the OS's routing.
+        bindAddress: String,
+        /// Ticks per second of a custom primary clock, see `Config::primary_frequency_hz`.
+        primaryFrequency: u32,
+        transport: Transport,
+        /// Restart the clock thread if it panics, see `Config::auto_restart`.
+        autoRestart: bool,
+        maxRestarts: u32,
+        /// File rewritten with the clock's state, empty for none, see `Config::heartbeat_file`.
+        heartbeatFile: String,
+    }
+
+    /// Raw fields of a clockkit protocol packet.
+    #[namespace = "bridge"]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct PacketData {
+        kind: u8,
+        sequence: u8,
+        clientRequestTime: i64,
+        serverReplyTime: i64,
+        clientReceiveTime: i64,
+    }
+
+    /// Raw counters of a PLC, see `Diagnostics`.
+    #[namespace = "bridge"]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct DiagnosticsData {
+        rtt: i64,
+        updates: u64,
+        timeouts: u64,
+        lastUpdate: i64,
+    }
+
+    /// Raw state of a PLC read at once, see `Snapshot`.
+    #[namespace = "bridge"]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct SnapshotData {
+        synchronized: bool,
+        value: i64,
+        offset: i64,
+    }
+
+    /// The protocol spoken with the server.
+    ///
+    /// Clockkit's protocol is UDP only, there is no TCP transport to fall back to on networks
+    /// blocking UDP. This leaves room for further transports without changing the API.
+    #[namespace = "bridge"]
+    #[derive(Debug, Hash)]
+    enum Transport {
+        Udp,
+    }
+
+    /// A server address and port.
+    #[namespace = "bridge"]
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Endpoint {
+        server: String,
+        port: u16,
     }
 
     #[namespace = "bridge"]
@@ -74,9 +248,66 @@ mod ffi {
         include!("clockkit/include/ClockKit/bridge.h");
 
         fn buildPLC(config: ConfigReader) -> SharedPtr<PhaseLockedClock>;
+        fn buildPLCWithPrimary(
+            config: ConfigReader,
+            primary: Box<PrimaryClockBox>,
+        ) -> SharedPtr<PhaseLockedClock>;
         fn setPhasePanic(clock: SharedPtr<PhaseLockedClock>, dur: i64);
         fn setUpdatePanic(clock: SharedPtr<PhaseLockedClock>, dur: i64);
-        fn getValue(clock: SharedPtr<PhaseLockedClock>) -> i64;
+        fn getPhasePanic(clock: SharedPtr<PhaseLockedClock>) -> i64;
+        fn getUpdatePanic(clock: SharedPtr<PhaseLockedClock>) -> i64;
+        fn setTimeout(clock: SharedPtr<PhaseLockedClock>, dur: i64);
+        fn getTimeout(clock: SharedPtr<PhaseLockedClock>) -> i64;
+        fn getLastSequence(clock: SharedPtr<PhaseLockedClock>) -> u32;
+        fn getDiagnostics(clock: SharedPtr<PhaseLockedClock>) -> DiagnosticsData;
+        fn getSyncAge(clock: &PhaseLockedClock) -> i64;
+        fn getLastRTT(clock: &PhaseLockedClock) -> i64;
+        fn reset(clock: SharedPtr<PhaseLockedClock>);
+        fn getSnapshot(clock: SharedPtr<PhaseLockedClock>) -> SnapshotData;
+        fn getValue(clock: &PhaseLockedClock) -> i64;
+        fn getOffset(clock: &PhaseLockedClock) -> i64;
+        fn getFrequencyRatio(clock: SharedPtr<PhaseLockedClock>) -> f64;
+        fn getServerTime(clock: SharedPtr<PhaseLockedClock>) -> i64;
+
+        fn buildClient(
+            server: String,
+            port: u16,
+            timeout: u32,
+            bindAddress: String,
+        ) -> SharedPtr<ClockClient>;
+        fn getClientValue(client: SharedPtr<ClockClient>) -> i64;
+        fn queryClientPacket(client: SharedPtr<ClockClient>) -> PacketData;
+        // Only used with the `packet_trace` feature.
+        #[allow(dead_code)]
+        fn setClientPacketTrace(client: SharedPtr<ClockClient>, trace: Box<PacketTraceBox>);
+        #[allow(dead_code)]
+        fn setPacketTrace(clock: SharedPtr<PhaseLockedClock>, trace: Box<PacketTraceBox>);
+
+        fn buildServer(port: u16) -> SharedPtr<ClockServer>;
+        fn buildServerWithSource(port: u16, source: Box<PrimaryClockBox>)
+            -> SharedPtr<ClockServer>;
+        fn runServer(server: SharedPtr<ClockServer>) -> Result<()>;
+        fn cancelServer(server: SharedPtr<ClockServer>);
+        fn clearServerCancel(server: SharedPtr<ClockServer>);
+
+        fn buildVariableClock() -> SharedPtr<VariableFrequencyClock>;
+        fn getVariableValue(clock: SharedPtr<VariableFrequencyClock>) -> i64;
+        fn setVariableFrequency(clock: SharedPtr<VariableFrequencyClock>, frequency: f64);
+        fn getVariableFrequency(clock: SharedPtr<VariableFrequencyClock>) -> f64;
+
+        fn getSystemValue() -> i64;
+    }
+    #[namespace = "bridge"]
+    extern "Rust" {
+        type PrimaryClockBox;
+
+        #[cxx_name = "primaryValue"]
+        fn primary_value(clock: &PrimaryClockBox) -> Result<i64>;
+
+        type PacketTraceBox;
+
+        #[cxx_name = "tracePacket"]
+        fn trace_packet(trace: &PacketTraceBox, sent: bool, packet: PacketData);
     }
     #[namespace = "dex"]
     unsafe extern "C++" {
@@ -84,8 +315,28 @@ mod ffi {
         type PhaseLockedClock;
 
         fn isSynchronized(&self) -> bool;
-        fn run1(clock: SharedPtr<PhaseLockedClock>);
+        fn hasEverSynchronized(&self) -> bool;
+        fn run1(clock: SharedPtr<PhaseLockedClock>) -> Result<()>;
         fn cancel(clock: SharedPtr<PhaseLockedClock>);
+        fn clearCancel(clock: SharedPtr<PhaseLockedClock>);
+        fn isCancelled(clock: &PhaseLockedClock) -> bool;
+        fn setPaused(clock: SharedPtr<PhaseLockedClock>, paused: bool);
+        fn isPaused(clock: &PhaseLockedClock) -> bool;
+    }
+    #[namespace = "dex"]
+    unsafe extern "C++" {
+        include!("clockkit/include/ClockKit/ClockClient.h");
+        type ClockClient;
+    }
+    #[namespace = "dex"]
+    unsafe extern "C++" {
+        include!("clockkit/include/ClockKit/ClockServer.h");
+        type ClockServer;
+    }
+    #[namespace = "dex"]
+    unsafe extern "C++" {
+        include!("clockkit/include/ClockKit/VariableFrequencyClock.h");
+        type VariableFrequencyClock;
     }
 }
 
@@ -97,14 +348,25 @@ impl Default for ffi::ConfigReader {
     /// timeout:1000
     /// phasePanic:5000
     /// updatePanic:5000000
+    /// updateInterval:200000
     /// ```
     fn default() -> Self {
+        // Shared with the `config!` macro, keep them in one place.
+        let defaults = config_macro::ConstConfig::DEFAULT;
         Self {
-            server: "127.0.0.1".to_owned(),
-            port: 4444,
-            timeout: 1000,
-            phasePanic: 5000,
-            updatePanic: 5000000,
+            server: defaults.server.to_owned(),
+            port: defaults.port,
+            timeout: defaults.timeout,
+            phasePanic: defaults.phase_panic,
+            updatePanic: defaults.update_panic,
+            updateInterval: defaults.update_interval,
+            fallbacks: Vec::new(),
+            bindAddress: String::new(),
+            primaryFrequency: 1_000_000,
+            transport: Transport::Udp,
+            autoRestart: false,
+            maxRestarts: 5,
+            heartbeatFile: String::new(),
         }
     }
 }
@@ -119,72 +381,1219 @@ impl ffi::ConfigReader {
     /// timeout:1000
     /// phasePanic:5000
     /// updatePanic:5000000
+    /// updateInterval:200000
+    /// ```
+    ///
+    /// Instead of `server` and `port`, a combined `endpoint:[::1]:4444` may be given, see
+    /// [`Self::endpoint`]. Later lines override earlier ones.
+    ///
+    /// The durations `timeout`, `phasePanic`, `updatePanic` and `updateInterval` are in μs, or
+    /// in the unit of an `s`, `ms` or `us` suffix, e.g. `updatePanic:5s`. An optional
+    /// `bindAddress:10.0.0.2` sets [`Self::bind_address`].
+    ///
+    /// Failing to open or read the file gives an [`Error::ConfigRead`] naming the path and the
+    /// cause.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{Config, Error};
+    /// let path = std::env::temp_dir().join("clockkit-missing.conf");
+    /// let err = Config::from_config_file(&path).unwrap_err();
+    /// assert!(matches!(&err, Error::ConfigRead { path: Some(p), source }
+    ///     if *p == path && source.kind() == std::io::ErrorKind::NotFound));
+    /// assert!(err
+    ///     .to_string()
+    ///     .starts_with(&format!("Could not read config file '{}': ", path.display())));
     /// ```
     #[cfg_attr(feature="tracing", tracing::instrument(level = "INFO", fields(path=%path.as_ref().display())))]
     pub fn from_config_file(path: impl AsRef<Path>) -> Result<Self, Error> {
-        let mut res = Self::default();
+        read_config_file(path.as_ref(), Self::from_reader)
+    }
+
+    /// Like [`Self::from_config_file`], but skip unknown keys instead of failing.
+    ///
+    /// This lets a config file written for a newer version load, the skipped keys are returned
+    /// as warnings. Prefer the strict variant where possible, as it also catches typos.
+    #[cfg_attr(feature="tracing", tracing::instrument(level = "INFO", fields(path=%path.as_ref().display())))]
+    pub fn from_config_file_lenient(path: impl AsRef<Path>) -> Result<(Self, Vec<String>), Error> {
+        read_config_file(path.as_ref(), Self::from_reader_lenient)
+    }
+
+    /// Create a new PLC config from the environment variables `CLOCKKIT_SERVER`,
+    /// `CLOCKKIT_PORT`, `CLOCKKIT_TIMEOUT`, `CLOCKKIT_PHASE_PANIC`, `CLOCKKIT_UPDATE_PANIC` and
+    /// `CLOCKKIT_UPDATE_INTERVAL`, using the defaults for unset ones.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{Config, Error};
+    /// std::env::set_var("CLOCKKIT_SERVER", "10.10.10.20");
+    /// std::env::set_var("CLOCKKIT_PORT", "1234");
+    /// let config = Config::from_env().unwrap();
+    /// assert_eq!(config.server, "10.10.10.20");
+    /// assert_eq!(config.port, 1234);
+    /// assert_eq!(config.timeout, Config::default().timeout);
+    ///
+    /// std::env::set_var("CLOCKKIT_TIMEOUT", "soon");
+    /// assert!(matches!(Config::from_env(), Err(Error::ConfigInvalid { .. })));
+    ///
+    /// std::env::remove_var("CLOCKKIT_TIMEOUT");
+    /// std::env::remove_var("CLOCKKIT_SERVER");
+    /// std::env::remove_var("CLOCKKIT_PORT");
+    /// assert_eq!(Config::from_env().unwrap(), Config::default());
+    /// ```
+    pub fn from_env() -> Result<Self, Error> {
+        Self::default().with_env()
+    }
+
+    /// Read `path` like [`Self::from_config_file`], then override settings from the environment
+    /// like [`Self::from_env`].
+    pub fn from_env_or_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_config_file(path)?.with_env()
+    }
+
+    /// Read the first `clockkit.conf` found in `$XDG_CONFIG_HOME/clockkit/`, `~/.config/clockkit/`
+    /// if `XDG_CONFIG_HOME` is unset, and the current directory, see [`Self::from_config_file`].
+    ///
+    /// Fails with [`Error::ConfigRead`], listing the searched paths, if there is none.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{Config, Error};
+    /// let home = std::env::temp_dir().join("clockkit-default-location");
+    /// std::fs::create_dir_all(home.join("clockkit")).unwrap();
+    /// std::env::set_var("XDG_CONFIG_HOME", &home);
+    /// assert!(matches!(Config::from_default_location(), Err(Error::ConfigRead { .. })));
+    ///
+    /// std::fs::write(home.join("clockkit/clockkit.conf"), "port:1234\n").unwrap();
+    /// let config = Config::from_default_location().unwrap();
+    /// # std::fs::remove_dir_all(&home).unwrap();
+    /// assert_eq!(config.port, 1234);
+    /// ```
+    pub fn from_default_location() -> Result<Self, Error> {
+        // Relative paths in XDG_CONFIG_HOME are invalid and to be ignored.
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+        let candidates: Vec<PathBuf> = config_home
+            .map(|dir| dir.join("clockkit"))
+            .into_iter()
+            .chain(std::iter::once(PathBuf::new()))
+            .map(|dir| dir.join("clockkit.conf"))
+            .collect();
+        match candidates.iter().find(|path| path.is_file()) {
+            Some(path) => Self::from_config_file(path),
+            None => Err(Error::ConfigRead {
+                path: None,
+                source: std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("no config file in {candidates:?}"),
+                ),
+            }),
+        }
+    }
 
-        let config = std::fs::read_to_string(path.as_ref())?;
-        for line in config.lines() {
-            if line.starts_with('#') {
-                continue;
+    /// Override the settings for which a `CLOCKKIT_*` environment variable is set.
+    fn with_env(mut self) -> Result<Self, Error> {
+        fn var<T: std::str::FromStr>(name: &str) -> Result<Option<T>, Error> {
+            let invalid = |reason| Error::ConfigInvalid {
+                key: name.to_string(),
+                reason,
+            };
+            match std::env::var(name) {
+                Ok(val) => val
+                    .trim()
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| invalid("not a valid value")),
+                Err(std::env::VarError::NotPresent) => Ok(None),
+                Err(std::env::VarError::NotUnicode(_)) => Err(invalid("not valid unicode")),
             }
-            let mut parts = line.trim().splitn(2, ':');
+        }
+        if let Some(server) = var("CLOCKKIT_SERVER")? {
+            self.server = server;
+        }
+        if let Some(port) = var("CLOCKKIT_PORT")? {
+            self.port = port;
+        }
+        if let Some(timeout) = var("CLOCKKIT_TIMEOUT")? {
+            self.timeout = timeout;
+        }
+        if let Some(phase_panic) = var("CLOCKKIT_PHASE_PANIC")? {
+            self.phasePanic = phase_panic;
+        }
+        if let Some(update_panic) = var("CLOCKKIT_UPDATE_PANIC")? {
+            self.updatePanic = update_panic;
+        }
+        if let Some(update_interval) = var("CLOCKKIT_UPDATE_INTERVAL")? {
+            self.updateInterval = update_interval;
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(config=?self, "Applied environment");
+        Ok(self)
+    }
+
+    /// Create a new PLC config from anything implementing [`std::io::Read`].
+    ///
+    /// The format is the same as for [`Self::from_config_file`]. Everything after a `#` is a
+    /// comment, and whitespace around keys and values, including the `\r` of CRLF line endings,
+    /// is ignored.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::Config;
+    /// let config = Config::from_reader(&b"# reference\nserver:10.10.10.20\nport:1234\n"[..]).unwrap();
+    /// assert_eq!(config.server, "10.10.10.20");
+    /// assert_eq!(config.port, 1234);
+    /// assert_eq!(config.timeout, Config::default().timeout);
+    ///
+    /// let err = Config::from_reader(&b"server:localhost\nport:none\n"[..]).unwrap_err();
+    /// assert_eq!(err.to_string(), "Invalid value for 'port' on line 2");
+    ///
+    /// let config = Config::from_reader(&b"endpoint:[fe80::1]:1234\n"[..]).unwrap();
+    /// assert_eq!((config.server.as_str(), config.port), ("fe80::1", 1234));
+    ///
+    /// let crlf = b"server:10.10.10.20 # prod\r\nport: 1234\r\ntimeout:2000\r";
+    /// let config = Config::from_reader(&crlf[..]).unwrap();
+    /// assert_eq!(config.server, "10.10.10.20");
+    /// assert_eq!(config.port, 1234);
+    /// assert_eq!(config.timeout, 2000);
+    ///
+    /// let units = b"timeout:2ms\nphasePanic:5000us\nupdatePanic:5s\nupdateInterval:100000\n";
+    /// let config = Config::from_reader(&units[..]).unwrap();
+    /// assert_eq!(config.timeout, 2000);
+    /// assert_eq!(config.phasePanic, 5000);
+    /// assert_eq!(config.updatePanic, 5_000_000);
+    /// assert_eq!(config.updateInterval, 100_000);
+    ///
+    /// let err = Config::from_reader(&b"updatePanic:5min\n"[..]).unwrap_err();
+    /// assert_eq!(err.to_string(), "Invalid value for 'updatePanic' on line 1");
+    ///
+    /// let err = Config::from_reader(&b"port:1234\ntransport:tcp\n"[..]).unwrap_err();
+    /// assert_eq!(err.to_string(), "Invalid value for 'transport' on line 2");
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "DEBUG", skip_all))]
+    pub fn from_reader(reader: impl Read) -> Result<Self, Error> {
+        Self::parse(reader, true).map(|(config, _)| config)
+    }
+
+    /// Like [`Self::from_reader`], but skip unknown keys and return them as warnings, see
+    /// [`Self::from_config_file_lenient`].
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{Config, Error};
+    /// let newer = b"server:10.10.10.20\nsmoothing:0.5\nport:1234\n";
+    /// assert!(matches!(
+    ///     Config::from_reader(&newer[..]),
+    ///     Err(Error::ConfigKey { line: 2, .. })
+    /// ));
+    ///
+    /// let (config, warnings) = Config::from_reader_lenient(&newer[..]).unwrap();
+    /// assert_eq!(config.server, "10.10.10.20");
+    /// assert_eq!(config.port, 1234);
+    /// assert_eq!(warnings, ["Invalid key 'smoothing' on line 2"]);
+    ///
+    /// // Invalid values of known keys are still errors.
+    /// assert!(Config::from_reader_lenient(&b"port:none\n"[..]).is_err());
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "DEBUG", skip_all))]
+    pub fn from_reader_lenient(reader: impl Read) -> Result<(Self, Vec<String>), Error> {
+        Self::parse(reader, false)
+    }
+
+    /// Parse a config file, failing on unknown keys if `strict` or else collecting them as
+    /// warnings.
+    fn parse(mut reader: impl Read, strict: bool) -> Result<(Self, Vec<String>), Error> {
+        let mut res = Self::default();
+        let mut warnings = Vec::new();
+
+        let mut config = String::new();
+        reader.read_to_string(&mut config)?;
+        for (idx, line) in config.lines().enumerate() {
+            let line = line
+                .split_once('#')
+                .map_or(line, |(content, _comment)| content);
+            let line_no = idx + 1;
+            let value_err = |key: &str| Error::ConfigValue {
+                line: line_no,
+                key: key.to_string(),
+            };
+            let mut parts = line.trim().splitn(2, ':').map(str::trim);
             if let Some(key) = parts.next() {
-                if let Some(ref val) = parts.next() {
+                if let Some(val) = parts.next() {
                     match key {
                         "server" => res.server = val.to_string(),
-                        "port" => res.port = val.parse().map_err(|_| Error::ConfigValue)?,
-                        "timeout" => res.timeout = val.parse().map_err(|_| Error::ConfigValue)?,
+                        "endpoint" => {
+                            let (server, port) =
+                                parse_endpoint(val).ok_or_else(|| value_err(key))?;
+                            res.server = server;
+                            res.port = port;
+                        }
+                        "port" => res.port = val.parse().map_err(|_| value_err(key))?,
+                        "timeout" => res.timeout = parse_usec(val).ok_or_else(|| value_err(key))?,
                         "phasePanic" => {
-                            res.phasePanic = val.parse().map_err(|_| Error::ConfigValue)?
+                            res.phasePanic = parse_usec(val).ok_or_else(|| value_err(key))?
                         }
                         "updatePanic" => {
-                            res.updatePanic = val.parse().map_err(|_| Error::ConfigValue)?
+                            res.updatePanic = parse_usec(val).ok_or_else(|| value_err(key))?
+                        }
+                        "updateInterval" => {
+                            res.updateInterval = parse_usec(val).ok_or_else(|| value_err(key))?
+                        }
+                        "bindAddress" => res.bindAddress = val.to_string(),
+                        "transport" => res.transport = val.parse().map_err(|_| value_err(key))?,
+                        _ => {
+                            let err = Error::ConfigKey {
+                                line: line_no,
+                                key: key.to_string(),
+                            };
+                            if strict {
+                                return Err(err);
+                            }
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!("{err}");
+                            warnings.push(err.to_string());
                         }
-                        _ => return Err(Error::ConfigKey(key.to_string())),
                     }
                 }
             }
         }
         #[cfg(feature = "tracing")]
-        tracing::debug!(config=?res, "Read config from file");
-        Ok(res)
+        tracing::debug!(config=?res, "Read config");
+        Ok((res, warnings))
+    }
+
+    /// Write the settings to `path` in the format read by [`Self::from_config_file`].
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{Config, Transport};
+    /// let path = std::env::temp_dir().join("clockkit-to-config-file.conf");
+    /// let config = Config::default()
+    ///     .server("10.10.10.20")
+    ///     .port(1234)
+    ///     .timeout(2000)
+    ///     .phase_panic(10000)
+    ///     .update_panic(10000000)
+    ///     .transport(Transport::Udp);
+    /// config.to_config_file(&path).unwrap();
+    /// assert!(std::fs::read_to_string(&path).unwrap().contains("transport:udp\n"));
+    ///
+    /// let read = Config::from_config_file(&path).unwrap();
+    /// # std::fs::remove_file(&path).unwrap();
+    /// assert_eq!(read, config);
+    /// ```
+    #[cfg_attr(feature="tracing", tracing::instrument(level = "INFO", skip(self), fields(path=%path.as_ref().display())))]
+    pub fn to_config_file(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let config = format!(
+            "server:{}\nport:{}\ntimeout:{}\nphasePanic:{}\nupdatePanic:{}\nupdateInterval:{}\n\
+             transport:{}\n",
+            self.server,
+            self.port,
+            self.timeout,
+            self.phasePanic,
+            self.updatePanic,
+            self.updateInterval,
+            self.transport
+        );
+        let config = if self.bindAddress.is_empty() {
+            config
+        } else {
+            format!("{config}bindAddress:{}\n", self.bindAddress)
+        };
+        std::fs::write(path.as_ref(), config).map_err(Error::ConfigWrite)
     }
 
+    /// Resolve `endpoint` and set up a UDP socket to it, the way the C++ client will.
+    ///
+    /// UDP has no handshake, so this can't tell whether a server listens; that only shows as
+    /// timeouts once the clock runs.
+    fn check_endpoint(&self, endpoint: &Endpoint) -> Result<(), Error> {
+        let addr = (endpoint.server.as_str(), endpoint.port)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| Error::Resolve(endpoint.server.clone()))?;
+        let local = match self.bindAddress.parse::<IpAddr>() {
+            Ok(ip) => ip,
+            Err(_) if addr.is_ipv4() => Ipv4Addr::UNSPECIFIED.into(),
+            Err(_) => Ipv6Addr::UNSPECIFIED.into(),
+        };
+        UdpSocket::bind((local, 0))
+            .and_then(|socket| socket.connect(addr))
+            .map_err(|e| Error::Connect(format!("{addr}: {e}")))
+    }
+
+    /// Pick the first endpoint whose server answers a query, trying `server:port` first and then
+    /// the fallbacks in order. Falls back to `server:port` if none answers.
+    fn select_endpoint(&self) -> Endpoint {
+        let primary = Endpoint {
+            server: self.server.clone(),
+            port: self.port,
+        };
+        if self.fallbacks.is_empty() {
+            return primary;
+        }
+        std::iter::once(primary.clone())
+            .chain(self.fallbacks.iter().cloned())
+            .find(|ep| {
+                // An endpoint that doesn't resolve can't answer, skip querying it.
+                self.check_endpoint(ep).is_ok()
+                    && ClockClient::with_bind_address(
+                        ep.server.clone(),
+                        ep.port,
+                        self.timeout,
+                        self.bindAddress.clone(),
+                    )
+                    .query_raw()
+                    .is_ok()
+            })
+            .unwrap_or(primary)
+    }
+
+    /// Check for settings with which the clock could never synchronize: a zero timeout or update
+    /// interval, an update panic not larger than the update interval or the timeout, a zero
+    /// primary frequency, a bind address which isn't an IP address, or an empty server or port 0,
+    /// for `server:port` and then for each fallback endpoint.
+    ///
+    /// An update panic not larger than the timeout would declare the clock out of sync before a
+    /// single request could complete.
+    ///
+    /// All problems are reported at once, e.g. for tooling checking a user-edited config, in the
+    /// order of the list above; building a clock fails with the first one.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{Config, Error};
+    /// assert!(Config::default().validate().is_ok());
+    ///
+    /// let errs = Config::default().port(0).validate().unwrap_err();
+    /// assert_eq!(errs.len(), 1);
+    /// assert_eq!(errs[0].to_string(), "Invalid config value for 'port': must not be 0");
+    /// assert!(matches!(
+    ///     Config::default().server(String::new()).validate().unwrap_err()[..],
+    ///     [Error::ConfigInvalid { .. }]
+    /// ));
+    /// assert!(Config::default().timeout(0).validate().is_err());
+    /// assert!(Config::default().update_interval(0).validate().is_err());
+    /// assert!(Config::default().update_interval(5000000).validate().is_err());
+    ///
+    /// let slow = Config::default().update_interval(1000).update_panic(2_000_000);
+    /// let errs = slow.clone().timeout(2_000_000).validate().unwrap_err();
+    /// assert_eq!(
+    ///     errs[0].to_string(),
+    ///     "Invalid config value for 'updatePanic': must be larger than timeout, or the clock \
+    ///      goes out of sync before a request completes"
+    /// );
+    /// assert!(slow.clone().timeout(2_000_001).validate().is_err());
+    /// assert!(slow.timeout(1_999_999).validate().is_ok());
+    /// assert!(Config::default().bind_address("eth0").validate().is_err());
+    /// assert!(Config::default().bind_address("127.0.0.1").validate().is_ok());
+    /// ```
+    ///
+    /// A config with several problems:
+    /// ```
+    /// # use clockkit::{Config, Error};
+    /// let errs = Config::default()
+    ///     .server(String::new())
+    ///     .port(0)
+    ///     .timeout(0)
+    ///     .update_panic(100)
+    ///     .validate()
+    ///     .unwrap_err();
+    /// let keys: Vec<_> = errs
+    ///     .iter()
+    ///     .map(|err| match err {
+    ///         Error::ConfigInvalid { key, .. } => key.as_str(),
+    ///         _ => unreachable!(),
+    ///     })
+    ///     .collect();
+    /// assert_eq!(keys, ["timeout", "updatePanic", "server", "port"]);
+    ///
+    /// // Every check fails, in the documented order.
+    /// let broken = Config::default()
+    ///     .timeout(0)
+    ///     .update_interval(0)
+    ///     .update_panic(0)
+    ///     .primary_frequency_hz(0)
+    ///     .bind_address("eth0")
+    ///     .server(String::new())
+    ///     .port(0);
+    /// let keys: Vec<_> = broken
+    ///     .validate()
+    ///     .unwrap_err()
+    ///     .into_iter()
+    ///     .map(|err| match err {
+    ///         Error::ConfigInvalid { key, .. } => key,
+    ///         _ => unreachable!(),
+    ///     })
+    ///     .collect();
+    /// assert_eq!(
+    ///     keys,
+    ///     [
+    ///         "timeout",
+    ///         "updateInterval",
+    ///         "updatePanic",
+    ///         "updatePanic",
+    ///         "primaryFrequency",
+    ///         "bindAddress",
+    ///         "server",
+    ///         "port",
+    ///     ]
+    /// );
+    /// // So building it fails with the timeout rather than the server.
+    /// let err = broken.try_build_clock().err().unwrap();
+    /// assert_eq!(err.to_string(), "Invalid config value for 'timeout': must not be 0");
+    ///
+    /// // Both orderings of updatePanic are reported.
+    /// let errs = Config::default().timeout(300000).update_panic(200000).validate().unwrap_err();
+    /// assert_eq!(errs.len(), 2);
+    ///
+    /// // Building the clock fails with the first problem.
+    /// let err = Config::default().server(String::new()).port(0).try_build_clock().err().unwrap();
+    /// assert_eq!(err.to_string(), "Invalid config value for 'server': must not be empty");
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<Error>> {
+        let mut errors = Vec::new();
+        let mut invalid = |key: &str, reason| {
+            errors.push(Error::ConfigInvalid {
+                key: key.to_string(),
+                reason,
+            })
+        };
+        if self.timeout == 0 {
+            invalid("timeout", "must not be 0");
+        }
+        if self.updateInterval == 0 {
+            invalid("updateInterval", "must not be 0");
+        }
+        if self.updatePanic <= self.updateInterval {
+            invalid("updatePanic", "must be larger than updateInterval");
+        }
+        if self.updatePanic <= self.timeout {
+            invalid(
+                "updatePanic",
+                "must be larger than timeout, or the clock goes out of sync before a request \
+                 completes",
+            );
+        }
+        if self.primaryFrequency == 0 {
+            invalid("primaryFrequency", "must not be 0");
+        }
+        if !self.bindAddress.is_empty() && self.bindAddress.parse::<IpAddr>().is_err() {
+            invalid("bindAddress", "must be an IP address");
+        }
+        let endpoints = std::iter::once((self.server.as_str(), self.port)).chain(
+            self.fallbacks
+                .iter()
+                .map(|ep| (ep.server.as_str(), ep.port)),
+        );
+        for (server, port) in endpoints {
+            if server.is_empty() {
+                invalid("server", "must not be empty");
+            }
+            if port == 0 {
+                invalid("port", "must not be 0");
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Describe settings that are legal but likely to degrade the clock, e.g. for a CLI to print
+    /// before starting. Unlike [`Self::validate`] this never rejects a config.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::Config;
+    /// assert!(Config::default().warnings().is_empty());
+    ///
+    /// let warnings = Config::default().timeout(500000).warnings();
+    /// assert_eq!(warnings.len(), 1);
+    /// assert!(warnings[0].starts_with("timeout (500000 μs) is not shorter than updateInterval"));
+    /// ```
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.phasePanic >= self.updatePanic {
+            warnings.push(format!(
+                "phasePanic ({} μs) is not smaller than updatePanic ({} μs), the clock will \
+                 tolerate offsets larger than the time it may go without updates",
+                self.phasePanic, self.updatePanic
+            ));
+        }
+        if self.timeout >= self.updateInterval {
+            warnings.push(format!(
+                "timeout ({} μs) is not shorter than updateInterval ({} μs), updates will be \
+                 delayed by slow replies",
+                self.timeout, self.updateInterval
+            ));
+        }
+        if self.updatePanic < self.updateInterval.saturating_mul(2) {
+            warnings.push(format!(
+                "updatePanic ({} μs) is less than twice updateInterval ({} μs), a single lost \
+                 reply makes the clock go out of sync",
+                self.updatePanic, self.updateInterval
+            ));
+        }
+        warnings
+    }
+
+    /// Build the clock like [`Self::try_build_clock`], but without resolving the server
+    /// eagerly: a server that doesn't resolve or can't be reached leaves the clock unsynchronized.
+    ///
+    /// # Panics
+    ///
+    /// If the config is [invalid](Self::validate).
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::Config;
+    /// # use std::time::Duration;
+    /// let clock = Config::default().server("no-such-host.invalid").build_clock();
+    /// assert_eq!(clock.current_endpoint().server, "no-such-host.invalid");
+    /// clock.start();
+    /// std::thread::sleep(Duration::from_millis(300));
+    /// clock.join().unwrap();
+    /// ```
     pub fn build_clock(self) -> PhaseLockedClock {
-        PhaseLockedClock {
-            ptr: ffi::buildPLC(self),
+        self.build_with(false, ffi::buildPLC)
+            .expect("invalid clock config")
+    }
+
+    /// Build a clock from a copy of the config, so that it can be reused for further clocks,
+    /// see [`Self::build_clock`].
+    ///
+    /// Panics if the config is invalid.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{ClockGroup, Config};
+    /// let base = Config::default().timeout(2000);
+    /// let group = ClockGroup::new(vec![
+    ///     base.build_clock_ref(),
+    ///     base.clone().port(4445).build_clock(),
+    /// ]);
+    /// assert_eq!(group.clocks()[0].current_endpoint().port, 4444);
+    /// assert_eq!(group.clocks()[1].current_endpoint().port, 4445);
+    /// ```
+    pub fn build_clock_ref(&self) -> PhaseLockedClock {
+        self.clone().build_clock()
+    }
+
+    /// Build the clock after [validating](Self::validate) the config.
+    ///
+    /// If fallback servers are configured, this blocks while querying each endpoint in turn,
+    /// at most `timeout` per endpoint, and locks to the first one answering.
+    ///
+    /// The selected server is resolved eagerly, failing with [`Error::Resolve`] if that doesn't
+    /// work, or with [`Error::Connect`] if no socket to it can be set up, e.g. from a bind
+    /// address that doesn't belong to this host.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{Config, Error};
+    /// assert!(Config::default().port(0).try_build_clock().is_err());
+    ///
+    /// let config = Config::default().server("no-such-host.invalid");
+    /// assert!(matches!(config.try_build_clock(), Err(Error::Resolve(_))));
+    ///
+    /// // An address reserved for documentation, so not one of this host's.
+    /// let config = Config::default().bind_address("192.0.2.1");
+    /// assert!(matches!(config.try_build_clock(), Err(Error::Connect(_))));
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub fn try_build_clock(self) -> Result<PhaseLockedClock, Error> {
+        self.build_with(true, ffi::buildPLC)
+    }
+
+    /// Build the clock like [`Self::try_build_clock`], but lock `primary` to the server instead of
+    /// the system clock.
+    ///
+    /// `primary` is called from the clock thread, where a panic ends the clock thread like an
+    /// exception from clockkit, see [`PhaseLockedClock::last_error`] and [`Self::auto_restart`].
+    /// It is also called by [`PhaseLockedClock::get_value`], the other readings of the value and
+    /// [`PhaseLockedClock::sync_age`], where a panic aborts the process.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{Config, SystemClock};
+    /// # use std::sync::atomic::{AtomicUsize, Ordering};
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4458);
+    /// server.start();
+    ///
+    /// // E.g. a PTP hardware clock, here the system clock 1s ahead.
+    /// static CALLS: AtomicUsize = AtomicUsize::new(0);
+    /// let primary = || {
+    ///     CALLS.fetch_add(1, Ordering::Relaxed);
+    ///     SystemClock::now() + 1_000_000
+    /// };
+    /// let clock = Config::default()
+    ///     .port(4458)
+    ///     .try_build_clock_with_primary(Box::new(primary))
+    ///     .unwrap();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// assert!(CALLS.load(Ordering::Relaxed) > 0);
+    /// ```
+    ///
+    /// A primary clock ticking at another rate than 1 MHz, see [`Self::primary_frequency_hz`]:
+    /// ```
+    /// # use clockkit::{Config, SystemClock};
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4468);
+    /// server.start();
+    ///
+    /// // A 10 MHz counter.
+    /// let primary = || SystemClock::now() * 10;
+    /// let clock = Config::default()
+    ///     .port(4468)
+    ///     .primary_frequency_hz(10_000_000)
+    ///     .try_build_clock_with_primary(Box::new(primary))
+    ///     .unwrap();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(500));
+    /// // The clock still runs in μs, at the server's pace.
+    /// let value = clock.get_value_raw().unwrap();
+    /// assert!((value - SystemClock::now()).abs() < 100_000);
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub fn try_build_clock_with_primary(
+        self,
+        primary: Box<dyn PrimaryClock>,
+    ) -> Result<PhaseLockedClock, Error> {
+        let primary = Box::new(PrimaryClockBox {
+            clock: primary,
+            frequency_hz: self.primaryFrequency,
+        });
+        self.build_with(true, |config| ffi::buildPLCWithPrimary(config, primary))
+    }
+
+    /// Validate and build, resolving and connecting to the selected endpoint first if `check`.
+    fn build_with(
+        mut self,
+        check: bool,
+        build: impl FnOnce(Self) -> SharedPtr<ffi::PhaseLockedClock>,
+    ) -> Result<PhaseLockedClock, Error> {
+        if let Err(mut errors) = self.validate() {
+            return Err(errors.swap_remove(0));
+        }
+        let endpoint = self.select_endpoint();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?endpoint, "Selected endpoint");
+        if check {
+            self.check_endpoint(&endpoint)?;
+        }
+        self.server = endpoint.server.clone();
+        self.port = endpoint.port;
+        let max_restarts = Some(self.maxRestarts).filter(|_| self.autoRestart);
+        let heartbeat = Some(&self.heartbeatFile)
+            .filter(|path| !path.is_empty())
+            .map(|path| {
+                Heartbeat::new(
+                    PathBuf::from(path),
+                    Duration::from_micros(self.updateInterval.into()),
+                )
+            });
+        Ok(PhaseLockedClock {
+            ptr: build(self),
             handle: Mutex::new(None),
+            monitor: Mutex::new(None),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            stats: Arc::new(Mutex::new(None)),
+            metrics: Arc::new(Metrics::default()),
+            callbacks: Arc::new(Mutex::new(Vec::new())),
+            watchers: Arc::new(Mutex::new(Vec::new())),
+            endpoint,
+            max_restarts,
+            heartbeat,
+            last_error: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    /// Set the server endpoints in order of preference.
+    ///
+    /// The first one replaces `server` and `port`, the remaining ones are used as fallbacks when
+    /// building the clock. An empty list leaves the config unchanged. Fallbacks are not part of
+    /// the config file format.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// let server = clockkit::Server::new(4447);
+    /// server.start();
+    /// # std::thread::sleep(std::time::Duration::from_millis(50));
+    ///
+    /// let clock = clockkit::Config::default()
+    ///     .servers(vec![
+    ///         ("127.0.0.1".to_string(), 4448),
+    ///         ("127.0.0.1".to_string(), 4447),
+    ///     ])
+    ///     .build_clock();
+    /// assert_eq!(clock.current_endpoint().port, 4447);
+    /// ```
+    #[must_use]
+    pub fn servers(mut self, servers: Vec<(String, u16)>) -> Self {
+        let mut endpoints = servers
+            .into_iter()
+            .map(|(server, port)| Endpoint { server, port });
+        if let Some(primary) = endpoints.next() {
+            self.server = primary.server;
+            self.port = primary.port;
+            self.fallbacks = endpoints.collect();
         }
+        self
     }
 
-    pub fn server(mut self, server: String) -> Self {
+    /// Set `server` and `port` from a combined `host:port`, with IPv6 addresses in brackets,
+    /// e.g. `[::1]:4444`.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::Config;
+    /// let config = Config::default().endpoint("[::1]:4445").unwrap();
+    /// assert_eq!((config.server.as_str(), config.port), ("::1", 4445));
+    ///
+    /// let config = Config::default().endpoint("time.local:1234").unwrap();
+    /// assert_eq!((config.server.as_str(), config.port), ("time.local", 1234));
+    ///
+    /// assert!(Config::default().endpoint("::1:4445").is_err());
+    /// assert!(Config::default().endpoint("time.local").is_err());
+    /// ```
+    pub fn endpoint(mut self, endpoint: &str) -> Result<Self, Error> {
+        let (server, port) = parse_endpoint(endpoint).ok_or(Error::ConfigInvalid {
+            key: "endpoint".to_string(),
+            reason: "expected host:port or [ipv6]:port",
+        })?;
         self.server = server;
+        self.port = port;
+        Ok(self)
+    }
+
+    /// Set the host name or IP address of the server, from a `&str`, `String` or `Cow<str>`.
+    #[must_use]
+    pub fn server(mut self, server: impl Into<String>) -> Self {
+        self.server = server.into();
         self
     }
 
+    #[must_use]
     pub fn port(mut self, port: u16) -> Self {
         self.port = port;
         self
     }
 
+    #[must_use]
+    pub fn timeout(mut self, timeout: u32) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the request timeout from a [`Duration`].
+    ///
+    /// Fails with [`Error::Overflow`] if the duration does not fit into `u32` microseconds.
+    pub fn timeout_dur(self, timeout: Duration) -> Result<Self, Error> {
+        let timeout = timeout
+            .as_micros()
+            .try_into()
+            .map_err(|_| Error::Overflow)?;
+        Ok(self.timeout(timeout))
+    }
+
+    #[must_use]
     pub fn phase_panic(mut self, phase_panic: u32) -> Self {
         self.phasePanic = phase_panic;
         self
     }
 
+    #[must_use]
     pub fn update_panic(mut self, update_panic: u32) -> Self {
         self.updatePanic = update_panic;
         self
     }
-}
 
-pub type Config = ffi::ConfigReader;
+    /// Set the phase panic from a [`Duration`], like [`PhaseLockedClock::set_phase_panic`] on a
+    /// running clock.
+    ///
+    /// Fails with [`Error::Overflow`] if the duration does not fit into `u32` microseconds.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{Config, Error};
+    /// # use std::time::Duration;
+    /// let config = Config::default()
+    ///     .phase_panic_dur(Duration::from_millis(5))?
+    ///     .update_panic_dur(Duration::from_secs(5))?;
+    /// assert_eq!(config, Config::default().phase_panic(5000).update_panic(5_000_000));
+    /// assert!(matches!(
+    ///     Config::default().update_panic_dur(Duration::from_secs(5000)),
+    ///     Err(Error::Overflow)
+    /// ));
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn phase_panic_dur(self, phase_panic: Duration) -> Result<Self, Error> {
+        let phase_panic = phase_panic
+            .as_micros()
+            .try_into()
+            .map_err(|_| Error::Overflow)?;
+        Ok(self.phase_panic(phase_panic))
+    }
 
-/// A clock locking its phase and frequency to a reference clock.
-///
-/// This class reads two clocks, a primary clock and a reference clock,
+    /// Set the update panic from a [`Duration`], like [`PhaseLockedClock::set_update_panic`] on
+    /// a running clock.
+    ///
+    /// Fails with [`Error::Overflow`] if the duration does not fit into `u32` microseconds.
+    pub fn update_panic_dur(self, update_panic: Duration) -> Result<Self, Error> {
+        let update_panic = update_panic
+            .as_micros()
+            .try_into()
+            .map_err(|_| Error::Overflow)?;
+        Ok(self.update_panic(update_panic))
+    }
+
+    /// Set the time between two updates from the server in μs.
+    ///
+    /// Longer intervals save bandwidth, but `updatePanic` must stay larger than the interval,
+    /// otherwise the clock declares itself out of sync between two updates.
+    #[must_use]
+    pub fn update_interval(mut self, update_interval: u32) -> Self {
+        self.updateInterval = update_interval;
+        self
+    }
+
+    /// Set the rate in Hz at which a custom primary clock ticks, 1 MHz by default.
+    ///
+    /// The values of the [`PrimaryClock`] given to [`Self::try_build_clock_with_primary`] are
+    /// in ticks of this frequency and converted to μs, the unit all of clockkit works in. So
+    /// timestamps, offsets and thresholds stay in μs whatever the primary frequency. The built-in
+    /// system clock always ticks in μs and ignores this.
+    #[must_use]
+    pub fn primary_frequency_hz(mut self, frequency_hz: u32) -> Self {
+        self.primaryFrequency = frequency_hz;
+        self
+    }
+
+    /// Restart the clock thread when it fails, instead of leaving the clock without updates.
+    ///
+    /// The clock thread fails with an exception from clockkit, e.g. a failing socket, or when a
+    /// custom primary clock panics on it, see [`Self::try_build_clock_with_primary`]. A
+    /// supervisor thread then runs the clock thread and respawns it after a failure, waiting
+    /// 100ms before the first restart and doubling that up to 10s for each further one. Each
+    /// restart is counted in [`Metrics::restarts`] and, with the `tracing` feature, logged as a
+    /// warning. Once [`Self::max_restarts`] restarts are used up the next failure is final: the
+    /// clock stops running, and [`PhaseLockedClock::last_error`] returns the exception as without
+    /// supervision. Not part of the config file format.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Config, SystemClock};
+    /// # use std::sync::atomic::{AtomicBool, Ordering};
+    /// # use std::time::{Duration, Instant};
+    /// let server = clockkit::Server::new(4473);
+    /// server.start();
+    ///
+    /// // A primary clock failing once.
+    /// static FAIL: AtomicBool = AtomicBool::new(false);
+    /// let primary = || {
+    ///     if FAIL.swap(false, Ordering::SeqCst) {
+    ///         panic!("hardware clock unavailable");
+    ///     }
+    ///     SystemClock::now()
+    /// };
+    /// let clock = Config::default()
+    ///     .port(4473)
+    ///     .auto_restart(true)
+    ///     .max_restarts(3)
+    ///     .try_build_clock_with_primary(Box::new(primary))
+    ///     .unwrap();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// assert_eq!(clock.metrics().restarts(), 0);
+    ///
+    /// FAIL.store(true, Ordering::SeqCst);
+    /// let start = Instant::now();
+    /// while clock.metrics().restarts() == 0 {
+    ///     assert!(start.elapsed() < Duration::from_secs(5));
+    ///     std::thread::sleep(Duration::from_millis(10));
+    /// }
+    /// assert!(clock.is_running());
+    /// assert!(clock.last_error().is_none());
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// clock.stop();
+    /// assert!(!clock.is_running());
+    /// ```
+    #[must_use]
+    pub fn auto_restart(mut self, auto_restart: bool) -> Self {
+        self.autoRestart = auto_restart;
+        self
+    }
+
+    /// Rewrite `path` with the clock's state every update interval, so that a watchdog can check
+    /// that a long-running client is alive and synchronized, by the file's mtime and contents.
+    ///
+    /// The file is written by the monitor thread while the clock runs, as lines of `key:value`
+    /// like the config file: `synchronized:true` or `synchronized:false`, and while synchronized
+    /// also the timestamp as `value` and the offset as `offset`, both in μs. It is replaced via
+    /// `path` with `.tmp` appended, so readers never see a partial file. Once the clock is
+    /// stopped the file is left as is and its mtime goes stale.
+    ///
+    /// Failing writes don't affect the clock; with the `tracing` feature the first failure is
+    /// logged as a warning. Non UTF-8 paths are converted lossily. Not part of the config file
+    /// format.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Config};
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4481);
+    /// server.start();
+    ///
+    /// let path = std::env::temp_dir().join("clockkit-heartbeat-4481");
+    /// # let _ = std::fs::remove_file(&path);
+    /// let clock = Config::default()
+    ///     .port(4481)
+    ///     .heartbeat_file(&path)
+    ///     .build_clock();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(500));
+    ///
+    /// let heartbeat = std::fs::read_to_string(&path).unwrap();
+    /// assert!(heartbeat.starts_with("synchronized:true\nvalue:"));
+    /// assert!(heartbeat.contains("\noffset:"));
+    /// clock.stop();
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    #[must_use]
+    pub fn heartbeat_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.heartbeatFile = path.into().to_string_lossy().into_owned();
+        self
+    }
+
+    /// Set how often [`Self::auto_restart`] restarts the clock thread at most, 5 by default.
+    #[must_use]
+    pub fn max_restarts(mut self, max_restarts: u32) -> Self {
+        self.maxRestarts = max_restarts;
+        self
+    }
+
+    /// Set the protocol to reach the server with, see [`Transport`] for the supported ones.
+    #[must_use]
+    pub fn transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Send from the local IP address `bind_address`, e.g. to pick the interface on a
+    /// multi-homed machine.
+    ///
+    /// Leaving it unset, or empty, keeps the OS's default routing. The address must be of the
+    /// same family as the server's, see [`Self::validate`].
+    #[must_use]
+    pub fn bind_address(mut self, bind_address: impl Into<String>) -> Self {
+        self.bindAddress = bind_address.into();
+        self
+    }
+}
+
+pub use ffi::ConfigReader as Config;
+
+impl std::str::FromStr for ffi::ConfigReader {
+    type Err = Error;
+
+    /// Parse a config in the format of [`Config::from_config_file`], see [`Config::from_reader`].
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::Config;
+    /// let config: Config = "server:10.10.10.20\nport:1234".parse().unwrap();
+    /// assert_eq!(config.server, "10.10.10.20");
+    /// assert_eq!(config.port, 1234);
+    /// assert!("port:none".parse::<Config>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Self::from_reader(s.as_bytes())
+    }
+}
+
+pub use ffi::Endpoint;
+
+pub use ffi::Transport;
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self::Udp
+    }
+}
+
+impl std::fmt::Display for Transport {
+    /// Write the name parsed by [`FromStr`](std::str::FromStr), as in config files.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Udp => f.write_str("udp"),
+            _ => Err(std::fmt::Error),
+        }
+    }
+}
+
+impl std::str::FromStr for Transport {
+    type Err = Error;
+
+    /// Parse `udp`; `tcp` fails with a hint that clockkit only speaks UDP.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::Transport;
+    /// assert_eq!("udp".parse::<Transport>().unwrap(), Transport::Udp);
+    /// let err = "tcp".parse::<Transport>().unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "Invalid config value for 'transport': clockkit only supports udp"
+    /// );
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = |reason| Error::ConfigInvalid {
+            key: "transport".to_string(),
+            reason,
+        };
+        match s {
+            "udp" => Ok(Self::Udp),
+            "tcp" => Err(invalid("clockkit only supports udp")),
+            _ => Err(invalid("expected udp")),
+        }
+    }
+}
+
+/// Mirror of [`Config`] for serde, the keys match the config file format.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(remote = "ffi::ConfigReader")]
+#[allow(non_snake_case)]
+struct ConfigReaderDef {
+    server: String,
+    port: u16,
+    timeout: u32,
+    phasePanic: u32,
+    updatePanic: u32,
+    #[serde(default = "default_update_interval")]
+    updateInterval: u32,
+    #[serde(default)]
+    fallbacks: Vec<Endpoint>,
+    #[serde(default)]
+    bindAddress: String,
+    #[serde(default = "default_primary_frequency")]
+    primaryFrequency: u32,
+    #[serde(default, with = "transport_serde")]
+    transport: Transport,
+    #[serde(default)]
+    autoRestart: bool,
+    #[serde(default = "default_max_restarts")]
+    maxRestarts: u32,
+    #[serde(default)]
+    heartbeatFile: String,
+}
+
+#[cfg(feature = "serde")]
+fn default_update_interval() -> u32 {
+    Config::default().updateInterval
+}
+
+#[cfg(feature = "serde")]
+fn default_primary_frequency() -> u32 {
+    Config::default().primaryFrequency
+}
+
+#[cfg(feature = "serde")]
+fn default_max_restarts() -> u32 {
+    Config::default().maxRestarts
+}
+
+/// [`Transport`] as its name in config files.
+#[cfg(feature = "serde")]
+mod transport_serde {
+    use super::Transport;
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        transport: &Transport,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match *transport {
+            Transport::Udp => serializer.serialize_str("udp"),
+            _ => Err(serde::ser::Error::custom("unknown transport")),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Transport, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Mirror of [`Endpoint`] for serde.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(remote = "ffi::Endpoint")]
+struct EndpointDef {
+    server: String,
+    port: u16,
+}
+
+/// Requires the `serde` feature.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ffi::Endpoint {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        EndpointDef::serialize(self, serializer)
+    }
+}
+
+/// Requires the `serde` feature.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ffi::Endpoint {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        EndpointDef::deserialize(deserializer)
+    }
+}
+
+/// Requires the `serde` feature.
+///
+/// Example:
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// # use clockkit::Config;
+/// let config = Config::default().server("10.10.10.20").phase_panic(10000);
+/// let json = serde_json::to_string(&config).unwrap();
+/// assert!(json.contains(r#""phasePanic":10000"#));
+///
+/// let read: Config = serde_json::from_str(&json).unwrap();
+/// assert_eq!(read, config);
+/// # }
+/// ```
+#[cfg(feature = "serde")]
+impl serde::Serialize for ffi::ConfigReader {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ConfigReaderDef::serialize(self, serializer)
+    }
+}
+
+/// Requires the `serde` feature.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ffi::ConfigReader {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ConfigReaderDef::deserialize(deserializer)
+    }
+}
+
+/// A clock locking its phase and frequency to a reference clock.
+///
+/// This class reads two clocks, a primary clock and a reference clock,
 /// both assumed to run at 1000000 Hz.
 /// The primary clock is usually a HighResolutionClock, whereas
 /// the reference clock is usually a ClockClient.
@@ -194,118 +1603,2403 @@ pub type Config = ffi::ConfigReader;
 /// Example:
 /// ```no_run
 /// # use clockkit;
-/// # use std::{thread,time};
-/// let mut clock = clockkit::Config::default().build_clock();
-/// clock.start();
-/// thread::sleep(time::Duration::from_millis(234));
-/// clock.get_value().expect("Failed to get value from clockkit server");
+/// # use std::time::Duration;
+/// let clock = clockkit::Config::default().build_clock();
+/// clock.start();
+/// clock.wait_until_synchronized(Duration::from_secs(1)).expect("Clock did not synchronize");
+/// clock.get_value().expect("Failed to get value from clockkit server");
+/// ```
+pub struct PhaseLockedClock {
+    ptr: SharedPtr<ffi::PhaseLockedClock>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+    monitor: Mutex<Option<Monitor>>,
+    subscribers: Subscribers,
+    stats: Stats,
+    metrics: Arc<Metrics>,
+    callbacks: Callbacks,
+    watchers: Watchers,
+    endpoint: Endpoint,
+    /// Restarts left to the supervisor, `None` without [`Config::auto_restart`].
+    max_restarts: Option<u32>,
+    heartbeat: Option<Heartbeat>,
+    last_error: LastError,
+}
+
+/// A change of the synchronization state of a [`PhaseLockedClock`], see
+/// [`PhaseLockedClock::subscribe`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncEvent {
+    /// The clock became synchronized.
+    Synchronized,
+    /// The clock went out of sync.
+    OutOfSync,
+}
+
+type Subscribers = Arc<Mutex<Vec<mpsc::Sender<SyncEvent>>>>;
+
+/// A server update as passed to the callbacks of [`PhaseLockedClock::on_update`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Update {
+    /// The offset in μs as by [`PhaseLockedClock::get_offset`], `None` if not synchronized.
+    pub offset: Option<i64>,
+    /// Round trip time of the request.
+    pub rtt: Duration,
+    pub synchronized: bool,
+}
+
+/// A callback of its own mutex, so that the monitor can call a snapshot of the list without
+/// holding the list's lock, which a callback registering another one takes.
+type Callback = Arc<Mutex<Box<dyn Fn(Update) + Send>>>;
+
+type Callbacks = Arc<Mutex<Vec<Callback>>>;
+
+/// Like [`Callbacks`], but called by the monitor on every check, e.g. to export the state.
+type Watchers = Callbacks;
+
+/// The message of the exception that ended the clock thread, see
+/// [`PhaseLockedClock::last_error`].
+type LastError = Arc<Mutex<Option<String>>>;
+
+/// Recorded offsets, `None` unless enabled with [`PhaseLockedClock::enable_offset_stats`].
+type Stats = Arc<Mutex<Option<VecDeque<i64>>>>;
+
+/// How often the monitor thread checks the synchronization state.
+const MONITOR_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Wait before the first restart of a panicked clock thread, doubled for each further one.
+const RESTART_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Longest wait between two restarts of a panicked clock thread.
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Number of offsets kept for [`OffsetStats`], i.e. the most recent 100s.
+const STATS_WINDOW: usize = 1000;
+
+/// Statistics of the offsets sampled while synchronized, see
+/// [`PhaseLockedClock::offset_stats`]. All values are in μs and zero without samples.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OffsetStats {
+    /// Number of samples in the window.
+    pub samples: usize,
+    pub min: i64,
+    pub max: i64,
+    pub mean: f64,
+    /// Population standard deviation, i.e. the jitter.
+    pub stddev: f64,
+}
+
+/// The sync quality of a [`PhaseLockedClock`] over some time, see [`PhaseLockedClock::measure`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QualityReport {
+    /// The offsets sampled while synchronized.
+    pub offsets: OffsetStats,
+    /// Number of samples taken while out of sync.
+    pub out_of_sync: usize,
+    /// Mean round trip time of the updates seen, zero without any.
+    pub mean_rtt: Duration,
+    pub max_rtt: Duration,
+    /// Number of successful requests during the measurement.
+    pub updates: u64,
+    /// Number of requests without a valid reply in time during the measurement.
+    pub timeouts: u64,
+}
+
+impl QualityReport {
+    /// The largest absolute offset in μs.
+    pub fn max_abs_offset(&self) -> u64 {
+        self.offsets
+            .min
+            .unsigned_abs()
+            .max(self.offsets.max.unsigned_abs())
+    }
+}
+
+/// Counters of a [`PhaseLockedClock`]'s requests to its server, see
+/// [`PhaseLockedClock::diagnostics`].
+///
+/// With the `serde` feature this is `Serialize`, with durations and times in μs like
+/// clockkit's, which holds for [`Metrics`] and [`Snapshot`] as well.
+///
+/// Example:
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// # use clockkit;
+/// # use std::time::Duration;
+/// let server = clockkit::Server::new(4470);
+/// server.start();
+///
+/// let clock = clockkit::Config::default().port(4470).build_clock();
+/// assert_eq!(
+///     serde_json::to_string(&clock.diagnostics()).unwrap(),
+///     r#"{"last_rtt":null,"updates":0,"timeouts":0,"last_update":null}"#
+/// );
+/// assert_eq!(
+///     serde_json::to_string(&clock.snapshot()).unwrap(),
+///     r#"{"value":null,"synchronized":false,"offset":null}"#
+/// );
+/// assert_eq!(
+///     serde_json::to_string(clock.metrics()).unwrap(),
+///     r#"{"updates":0,"timeouts":0,"out_of_sync_transitions":0,"restarts":0}"#
+/// );
+///
+/// clock.start();
+/// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+/// std::thread::sleep(Duration::from_millis(500));
+/// let json = serde_json::to_value(clock.diagnostics()).unwrap();
+/// assert!(json["last_rtt"].as_u64().unwrap() < 1_000_000);
+/// assert!(json["last_update"].as_i64().unwrap() > 0);
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diagnostics {
+    /// Round trip time of the last successful request, `None` before the first one.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "usec::duration"))]
+    pub last_rtt: Option<Duration>,
+    /// Number of successful requests.
+    pub updates: u64,
+    /// Number of requests without a valid reply in time, including network errors.
+    pub timeouts: u64,
+    /// Local time of the last successful request.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "usec::system_time"))]
+    pub last_update: Option<SystemTime>,
+}
+
+/// Serialization of durations and times in μs.
+#[cfg(feature = "serde")]
+mod usec {
+    use super::{Error, Timestamp};
+    use serde::Serializer;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    pub fn duration<S: Serializer>(
+        dur: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match dur {
+            // Saturate, u64 μs last for 584,000 years.
+            Some(dur) => serializer.serialize_u64(dur.as_micros().try_into().unwrap_or(u64::MAX)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn system_time<S: Serializer>(
+        time: &Option<SystemTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let usec = time.map(|time| match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => i64::try_from(since.as_micros()).unwrap_or(i64::MAX),
+            Err(before) => i64::try_from(before.duration().as_micros()).map_or(i64::MIN, |us| -us),
+        });
+        timestamp_usec(usec, serializer)
+    }
+
+    pub fn timestamp<S: Serializer>(
+        ts: &Result<Timestamp, Error>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        timestamp_usec(ts.as_ref().ok().map(Timestamp::as_micros), serializer)
+    }
+
+    fn timestamp_usec<S: Serializer>(usec: Option<i64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match usec {
+            Some(usec) => serializer.serialize_i64(usec),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Counters of a [`PhaseLockedClock`] that can be read without locking, see
+/// [`PhaseLockedClock::metrics`].
+///
+/// They are updated by the clock's monitor thread every 100ms while the clock runs, so they may
+/// lag behind [`PhaseLockedClock::diagnostics`] by that much.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Metrics {
+    updates: AtomicU64,
+    timeouts: AtomicU64,
+    #[cfg_attr(feature = "serde", serde(rename = "out_of_sync_transitions"))]
+    out_of_sync: AtomicU64,
+    restarts: AtomicU64,
+}
+
+impl Metrics {
+    /// Number of successful requests to the server.
+    pub fn updates(&self) -> u64 {
+        self.updates.load(Ordering::Relaxed)
+    }
+
+    /// Number of requests without a valid reply in time.
+    pub fn timeouts(&self) -> u64 {
+        self.timeouts.load(Ordering::Relaxed)
+    }
+
+    /// Number of times the clock lost its synchronization.
+    pub fn out_of_sync_transitions(&self) -> u64 {
+        self.out_of_sync.load(Ordering::Relaxed)
+    }
+
+    /// Number of times the clock thread was restarted after a panic, see
+    /// [`Config::auto_restart`]. Counted right away, not by the monitor thread.
+    pub fn restarts(&self) -> u64 {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    fn refresh(&self, plc: &SharedPtr<ffi::PhaseLockedClock>) -> ffi::DiagnosticsData {
+        let data = ffi::getDiagnostics(plc.clone());
+        self.updates.store(data.updates, Ordering::Relaxed);
+        self.timeouts.store(data.timeouts, Ordering::Relaxed);
+        data
+    }
+}
+
+/// The state of a [`PhaseLockedClock`] at one point in time, see
+/// [`PhaseLockedClock::snapshot`].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Snapshot {
+    /// The timestamp, [`Error::OutOfSync`] if not synchronized.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "usec::timestamp"))]
+    pub value: Result<Timestamp, Error>,
+    pub synchronized: bool,
+    /// The offset in μs as by [`PhaseLockedClock::get_offset`], `None` if not synchronized.
+    pub offset: Option<i64>,
+}
+
+impl From<ffi::SnapshotData> for Snapshot {
+    fn from(data: ffi::SnapshotData) -> Self {
+        let valid = |usec| Some(usec).filter(|&usec| data.synchronized && usec != USEC_INVALID);
+        Self {
+            value: valid(data.value)
+                .map(Timestamp::from_micros)
+                .ok_or(Error::OutOfSync),
+            synchronized: data.synchronized,
+            offset: valid(data.offset),
+        }
+    }
+}
+
+impl From<ffi::DiagnosticsData> for Diagnostics {
+    fn from(data: ffi::DiagnosticsData) -> Self {
+        let last_update = match data.lastUpdate {
+            USEC_INVALID => None,
+            usec => Timestamp::from_micros(usec).to_system_time().ok(),
+        };
+        Self {
+            last_rtt: last_update.map(|_| make_duration(data.rtt)),
+            updates: data.updates,
+            timeouts: data.timeouts,
+            last_update,
+        }
+    }
+}
+
+impl OffsetStats {
+    fn from_samples(samples: &VecDeque<i64>) -> Self {
+        let (Some(&min), Some(&max)) = (samples.iter().min(), samples.iter().max()) else {
+            return Self::default();
+        };
+        let n = samples.len() as f64;
+        let mean = samples.iter().map(|&o| o as f64).sum::<f64>() / n;
+        let variance = samples
+            .iter()
+            .map(|&o| (o as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        Self {
+            samples: samples.len(),
+            min,
+            max,
+            mean,
+            stddev: variance.sqrt(),
+        }
+    }
+}
+
+/// A thread watching a PLC and reporting changes of its synchronization state to the subscribers,
+/// the metrics and, with the `tracing` feature, as tracing events.
+struct Monitor {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Monitor {
+    fn spawn(
+        plc: SharedPtr<ffi::PhaseLockedClock>,
+        subscribers: Subscribers,
+        stats: Stats,
+        metrics: Arc<Metrics>,
+        callbacks: Callbacks,
+        watchers: Watchers,
+        mut heartbeat: Option<Heartbeat>,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let handle = std::thread::spawn(move || {
+            let mut synchronized = false;
+            // The counter survives restarts, don't report updates of an earlier run.
+            let mut updates = metrics.refresh(&plc).updates;
+            // The offset is invalid once out of sync, so remember the last valid one.
+            #[cfg(feature = "tracing")]
+            let mut offset = USEC_INVALID;
+            while !stopped.load(Ordering::Relaxed) {
+                std::thread::sleep(MONITOR_INTERVAL);
+                let data = metrics.refresh(&plc);
+                let now = plc.isSynchronized();
+                let current = if now {
+                    ffi::getOffset(&plc)
+                } else {
+                    USEC_INVALID
+                };
+                let update = Update {
+                    offset: Some(current).filter(|&offset| offset != USEC_INVALID),
+                    rtt: make_duration(data.rtt),
+                    synchronized: now,
+                };
+                let notify = |callbacks: &Callbacks| {
+                    let snapshot = match callbacks.lock() {
+                        Ok(guard) => guard.clone(),
+                        Err(poisoned) => poisoned.into_inner().clone(),
+                    };
+                    for callback in snapshot {
+                        let callback = match callback.lock() {
+                            Ok(guard) => guard,
+                            Err(poisoned) => poisoned.into_inner(),
+                        };
+                        callback(update);
+                    }
+                };
+                notify(&watchers);
+                if let Some(heartbeat) = heartbeat.as_mut() {
+                    let value = if now {
+                        ffi::getValue(&plc)
+                    } else {
+                        USEC_INVALID
+                    };
+                    heartbeat.beat(now, value, current);
+                }
+                if data.updates != updates {
+                    updates = data.updates;
+                    notify(&callbacks);
+                }
+                if current != USEC_INVALID {
+                    #[cfg(feature = "tracing")]
+                    {
+                        offset = current;
+                    }
+                    let mut stats = match stats.lock() {
+                        Ok(guard) => guard,
+                        Err(poisoned) => poisoned.into_inner(),
+                    };
+                    if let Some(samples) = (*stats).as_mut() {
+                        if samples.len() == STATS_WINDOW {
+                            samples.pop_front();
+                        }
+                        samples.push_back(current);
+                    }
+                }
+                if now == synchronized {
+                    continue;
+                }
+                synchronized = now;
+                let event = if synchronized {
+                    #[cfg(feature = "tracing")]
+                    tracing::info!(offset, "PhaseLockedClock synchronized");
+                    SyncEvent::Synchronized
+                } else {
+                    metrics.out_of_sync.fetch_add(1, Ordering::Relaxed);
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(last_offset = offset, "PhaseLockedClock out of sync");
+                    SyncEvent::OutOfSync
+                };
+                if let Ok(mut subs) = subscribers.lock() {
+                    subs.retain(|tx| tx.send(event).is_ok());
+                }
+            }
+            metrics.refresh(&plc);
+            // Disconnect the receivers, the clock has been stopped.
+            match subscribers.lock() {
+                Ok(mut subs) => subs.clear(),
+                Err(poisoned) => poisoned.into_inner().clear(),
+            }
+        });
+        Self { stop, handle }
+    }
+
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if self.handle.join().is_err() {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("PhaseLockedClock monitor thread panicked");
+        }
+    }
+}
+
+/// Run the clock thread, failing with the message of an exception ending it.
+fn run_clock(plc: SharedPtr<ffi::PhaseLockedClock>) -> Result<(), String> {
+    ffi::run1(plc).map_err(|e| e.what().to_owned())
+}
+
+/// Run the clock thread, keeping the message of an exception ending it in `last_error`.
+fn run_clock_keeping_error(plc: SharedPtr<ffi::PhaseLockedClock>, last_error: &LastError) {
+    if let Err(message) = run_clock(plc) {
+        #[cfg(feature = "tracing")]
+        tracing::error!(error = %message, "PhaseLockedClock thread failed");
+        keep_error(last_error, message);
+    }
+}
+
+fn keep_error(last_error: &LastError, message: String) {
+    match last_error.lock() {
+        Ok(mut guard) => *guard = Some(message),
+        Err(poisoned) => *poisoned.into_inner() = Some(message),
+    }
+}
+
+/// Run the clock thread, restarting it up to `max_restarts` times if it fails with an exception
+/// or panics.
+///
+/// Runs in place of the clock thread, so that a final panic is passed on to its handle. A final
+/// exception is kept in `last_error`, the ones restarted from are only counted.
+fn supervise(
+    plc: SharedPtr<ffi::PhaseLockedClock>,
+    max_restarts: u32,
+    metrics: &Metrics,
+    last_error: &LastError,
+) {
+    let mut backoff = RESTART_BACKOFF;
+    for restart in 1.. {
+        let worker = plc.clone();
+        let failure = match std::thread::spawn(move || run_clock(worker)).join() {
+            Ok(Ok(())) => return,
+            Ok(Err(message)) => Ok(message),
+            Err(panic) => Err(panic),
+        };
+        if ffi::isCancelled(&plc) || restart > max_restarts {
+            #[cfg(feature = "tracing")]
+            tracing::error!(max_restarts, "PhaseLockedClock thread failed, giving up");
+            match failure {
+                Ok(message) => keep_error(last_error, message),
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+            return;
+        }
+        metrics.restarts.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            restart,
+            ?backoff,
+            error = failure.as_ref().map_or("panic", String::as_str),
+            "PhaseLockedClock thread failed, restarting"
+        );
+        // Wait in slices, so that stopping the clock isn't delayed by the backoff.
+        let wake = Instant::now() + backoff;
+        while !ffi::isCancelled(&plc) && Instant::now() < wake {
+            std::thread::sleep(
+                MONITOR_INTERVAL.min(wake.saturating_duration_since(Instant::now())),
+            );
+        }
+        if ffi::isCancelled(&plc) {
+            return;
+        }
+        backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+    }
+}
+
+/// Open the config file at `path` and parse it, adding the path to read errors.
+fn read_config_file<T>(
+    path: &Path,
+    parse: impl FnOnce(std::fs::File) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let read_err = |source| Error::ConfigRead {
+        path: Some(path.to_path_buf()),
+        source,
+    };
+    let file = std::fs::File::open(path).map_err(read_err)?;
+    parse(file).map_err(|err| match err {
+        Error::ConfigRead { path: None, source } => read_err(source),
+        err => err,
+    })
+}
+
+/// Helper function to split `host:port` or `[ipv6]:port` into server and port.
+fn parse_endpoint(endpoint: &str) -> Option<(String, u16)> {
+    let endpoint = endpoint.trim();
+    let (host, port) = match endpoint.strip_prefix('[') {
+        Some(rest) => rest.split_once("]:")?,
+        None => endpoint.rsplit_once(':')?,
+    };
+    // An unbracketed IPv6 address is ambiguous.
+    if host.is_empty() || (!endpoint.starts_with('[') && host.contains(':')) {
+        return None;
+    }
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+/// Helper function to parse a config duration in μs, with an optional `s`, `ms` or `us` suffix.
+fn parse_usec(val: &str) -> Option<u32> {
+    let split = val.find(|c: char| !c.is_ascii_digit()).unwrap_or(val.len());
+    let (number, unit) = val.split_at(split);
+    let factor = match unit.trim_start() {
+        "" | "us" | "μs" => 1,
+        "ms" => 1_000,
+        "s" => 1_000_000,
+        _ => return None,
+    };
+    number.parse::<u32>().ok()?.checked_mul(factor)
+}
+
+/// Helper function to create a Duration from a duration in μs, clamping negative values to zero.
+fn make_duration(usec: i64) -> Duration {
+    Duration::from_micros(usec.try_into().unwrap_or(0))
+}
+
+/// Requires the `time` feature, see [`Timestamp::to_offset_datetime`].
+#[cfg(feature = "time")]
+impl TryFrom<Timestamp> for time::OffsetDateTime {
+    type Error = Error;
+
+    fn try_from(ts: Timestamp) -> Result<Self, Error> {
+        ts.to_offset_datetime()
+    }
+}
+
+impl Timestamp {
+    /// Convert to a `DateTime`, requires the `chrono` feature.
+    ///
+    /// Fails with [`Error::Overflow`] outside of the range of `DateTime`.
+    #[cfg(feature = "chrono")]
+    pub fn to_datetime(&self) -> Result<DateTime<Utc>, Error> {
+        let (sec, nsec) = self.as_secs_nanos();
+        DateTime::from_timestamp(sec, nsec).ok_or(Error::Overflow)
+    }
+
+    /// Convert to a `time::OffsetDateTime` in UTC, requires the `time` feature.
+    ///
+    /// Fails with [`Error::Overflow`] outside of the range of `OffsetDateTime`, which is ±9999
+    /// years unless the `time` crate's `large-dates` feature is enabled.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::Timestamp;
+    /// let dt = Timestamp::from_micros(-1).to_offset_datetime().unwrap();
+    /// assert_eq!(dt.unix_timestamp_nanos(), -1000);
+    /// let dt: time::OffsetDateTime = Timestamp::from_micros(1_234_567).try_into().unwrap();
+    /// assert_eq!((dt.second(), dt.microsecond()), (1, 234_567));
+    /// assert!(Timestamp::from_micros(i64::MAX).to_offset_datetime().is_err());
+    /// ```
+    #[cfg(feature = "time")]
+    pub fn to_offset_datetime(&self) -> Result<time::OffsetDateTime, Error> {
+        let nsec = i128::from(self.as_micros()) * 1000;
+        time::OffsetDateTime::from_unix_timestamp_nanos(nsec).map_err(|_| Error::Overflow)
+    }
+
+    /// Convert to a `SystemTime`, failing with [`Error::Overflow`] outside of its range.
+    pub fn to_system_time(&self) -> Result<SystemTime, Error> {
+        match self.abs_since_epoch() {
+            (since_epoch, false) => UNIX_EPOCH.checked_add(since_epoch),
+            (before_epoch, true) => UNIX_EPOCH.checked_sub(before_epoch),
+        }
+        .ok_or(Error::Overflow)
+    }
+}
+
+impl PhaseLockedClock {
+    /// Get the timestamp, requires the `chrono` feature.
+    ///
+    /// Fails with [`Error::OutOfSync`] if the clock is not synchronized, e.g. before it was
+    /// started.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Error};
+    /// let clock = clockkit::Config::default().port(4453).build_clock();
+    /// assert!(matches!(clock.get_value(), Err(Error::OutOfSync)));
+    /// assert!(matches!(clock.get_value_raw(), Err(Error::OutOfSync)));
+    /// ```
+    #[cfg(feature = "chrono")]
+    pub fn get_value(&self) -> Result<chrono::DateTime<Utc>, Error> {
+        self.get_timestamp()?.to_datetime()
+    }
+
+    /// Get the synchronized value, or the local [`SystemClock`] while out of sync, requires the
+    /// `chrono` feature.
+    ///
+    /// The flag tells which one it is, `true` for the synchronized value. This keeps e.g. a data
+    /// logger timestamping through an outage, with the samples marked as not synchronized.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use chrono::Utc;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4474);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4474).build_clock();
+    /// let (local, synchronized) = clock.value_or_local();
+    /// assert!(!synchronized);
+    /// assert!((Utc::now() - local).num_seconds() < 1);
+    ///
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// assert!(clock.value_or_local().1);
+    /// ```
+    #[cfg(feature = "chrono")]
+    pub fn value_or_local(&self) -> (chrono::DateTime<Utc>, bool) {
+        if let Ok(value) = self.get_value() {
+            return (value, true);
+        }
+        let local = Timestamp::from_micros(SystemClock::now())
+            .to_datetime()
+            // Only fails for a system time outside of chrono's range of ±262000 years.
+            .unwrap_or_else(|_| Utc::now());
+        (local, false)
+    }
+
+    /// Get the timestamp like [`Self::get_value`], but give up with [`Error::Timeout`] if
+    /// reading it takes longer than `deadline`, requires the `chrono` feature.
+    ///
+    /// The C++ clock is read on a separate thread, which finishes in the background after a
+    /// timeout. Spawning it costs some μs, so this is a guard for loops which must not stall
+    /// on contention rather than a faster [`Self::get_value`].
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Error};
+    /// # use std::time::Duration;
+    /// let clock = clockkit::Config::default().port(4453).build_clock();
+    /// assert!(matches!(
+    ///     clock.get_value_timeout(Duration::from_secs(1)),
+    ///     Err(Error::OutOfSync)
+    /// ));
+    /// ```
+    #[cfg(feature = "chrono")]
+    pub fn get_value_timeout(&self, deadline: Duration) -> Result<DateTime<Utc>, Error> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let plc = self.ptr.clone();
+        std::thread::spawn(move || {
+            // The receiver is gone after a timeout, the value is no use then.
+            let _ = tx.send(ffi::getValue(&plc));
+        });
+        match rx.recv_timeout(deadline) {
+            Ok(USEC_INVALID) => Err(Error::OutOfSync),
+            Ok(usec) => Timestamp::from_micros(usec).to_datetime(),
+            Err(_) => Err(Error::Timeout),
+        }
+    }
+
+    /// Get the timestamp as [`Timestamp`].
+    ///
+    /// Fails with [`Error::OutOfSync`] if the clock is not synchronized.
+    pub fn get_timestamp(&self) -> Result<Timestamp, Error> {
+        self.get_value_raw().map(Timestamp::from_micros)
+    }
+
+    /// Get the timestamp as [`SystemTime`], for code which does not use `chrono`.
+    ///
+    /// Fails with [`Error::OutOfSync`] if the clock is not synchronized.
+    pub fn get_value_system(&self) -> Result<SystemTime, Error> {
+        self.get_timestamp()?.to_system_time()
+    }
+
+    /// Get the timestamp as μs since the Unix epoch, as reported by the server.
+    ///
+    /// This skips the conversion to `DateTime` and is meant for tight loops, e.g. when only the
+    /// difference between two timestamps is of interest. Fails with [`Error::OutOfSync`] if the
+    /// clock is not synchronized.
+    pub fn get_value_raw(&self) -> Result<i64, Error> {
+        // The C++ clock returns the invalid sentinel only while out of sync.
+        match ffi::getValue(&self.ptr) {
+            USEC_INVALID => Err(Error::OutOfSync),
+            usec => Ok(usec),
+        }
+    }
+
+    /// Get the time the server reported in the last successful update, requires the `chrono`
+    /// feature.
+    ///
+    /// Unlike [`Self::get_value`], which is the disciplined local estimate of the server's time
+    /// right now, this is the raw sample from the server's last reply. It does not advance
+    /// between updates and includes the network jitter the PLC smooths out. Fails with
+    /// [`Error::OutOfSync`] before the first successful update.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Error};
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4460);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4460).build_clock();
+    /// assert!(matches!(clock.last_server_value(), Err(Error::OutOfSync)));
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(500));
+    /// let sample = clock.last_server_value().unwrap();
+    /// assert!(sample <= clock.get_value().unwrap());
+    /// ```
+    #[cfg(feature = "chrono")]
+    pub fn last_server_value(&self) -> Result<DateTime<Utc>, Error> {
+        self.last_server_timestamp()?.to_datetime()
+    }
+
+    /// Get the time the server reported in the last successful update as [`Timestamp`], see
+    /// [`Self::last_server_value`].
+    pub fn last_server_timestamp(&self) -> Result<Timestamp, Error> {
+        match ffi::getServerTime(self.ptr.clone()) {
+            USEC_INVALID => Err(Error::OutOfSync),
+            usec => Ok(Timestamp::from_micros(usec)),
+        }
+    }
+
+    /// Get the phase offset of the reference clock relative to this clock in μs.
+    ///
+    /// A positive offset means the reference clock is ahead. Fails with [`Error::OutOfSync`] if
+    /// the clock is not synchronized.
+    pub fn get_offset(&self) -> Result<i64, Error> {
+        match ffi::getOffset(&self.ptr) {
+            USEC_INVALID => Err(Error::OutOfSync),
+            offset => Ok(offset),
+        }
+    }
+
+    /// Get the value, synchronization state and offset, read together.
+    ///
+    /// Separate calls of [`Self::get_timestamp`], [`Self::is_synchronized`] and
+    /// [`Self::get_offset`] may straddle an update of the clock, so their results need not
+    /// match; the snapshot is read in one go while the clock's state is locked.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Error};
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4467);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4467).build_clock();
+    /// let snapshot = clock.snapshot();
+    /// assert!(!snapshot.synchronized);
+    /// assert!(matches!(snapshot.value, Err(Error::OutOfSync)));
+    /// assert_eq!(snapshot.offset, None);
+    ///
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// let snapshot = clock.snapshot();
+    /// if snapshot.synchronized {
+    ///     assert!(snapshot.value.is_ok());
+    ///     assert!(snapshot.offset.is_some());
+    /// }
+    /// ```
+    pub fn snapshot(&self) -> Snapshot {
+        ffi::getSnapshot(self.ptr.clone()).into()
+    }
+
+    /// Get the rate of the clock relative to its local primary clock.
+    ///
+    /// To stay locked to the server the PLC continuously adjusts this multiplier, 1.0 means it
+    /// runs at the nominal rate. Values wandering far from 1.0 over time indicate an unstable
+    /// link to the server or a bad local oscillator.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4459);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4459).build_clock();
+    /// assert_eq!(clock.frequency_ratio(), 1.0);
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// assert!((clock.frequency_ratio() - 1.0).abs() < 0.01);
+    /// ```
+    pub fn frequency_ratio(&self) -> f64 {
+        ffi::getFrequencyRatio(self.ptr.clone())
+    }
+
+    /// The server endpoint this clock is locked to.
+    pub fn current_endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+
+    /// Check whether the PLC is synchronized.
+    pub fn is_synchronized(&self) -> bool {
+        self.ptr.isSynchronized()
+    }
+
+    /// Check whether the PLC has been synchronized at any time since it was built.
+    ///
+    /// Unlike [`Self::is_synchronized`] this stays `true` once set, even across
+    /// [`Self::reset`] and restarts. So a clock that is out of sync and has never synchronized is
+    /// still starting up, while one that has lost a good connection reports `true` here.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4479);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4479).build_clock();
+    /// assert!(!clock.has_ever_synchronized());
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(500));
+    /// assert!(clock.has_ever_synchronized());
+    ///
+    /// clock.reset().unwrap();
+    /// assert!(!clock.is_synchronized());
+    /// assert!(clock.has_ever_synchronized());
+    /// ```
+    pub fn has_ever_synchronized(&self) -> bool {
+        self.ptr.hasEverSynchronized()
+    }
+
+    /// Run the PLC in its own thread
+    ///
+    /// Panics if the internal mutex is poisoned, see [`Self::try_start`] for a fallible version.
+    pub fn start(&self) {
+        self.try_start()
+            .expect("Unable to start PhaseLockedClock due to poisened mutex");
+    }
+
+    /// Run the PLC in its own thread, failing with [`Error::LockPoisoned`] if the mutex guarding
+    /// the thread handle is poisoned.
+    ///
+    /// Starting a clock which is already running does nothing.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub fn try_start(&self) -> Result<(), Error> {
+        let mut guard = self.handle.lock().map_err(|_| Error::LockPoisoned)?;
+        // Only start the clock if there is no handle present, otherwise it's running.
+        if (*guard).is_none() {
+            let plc = self.ptr.clone();
+            ffi::clearCancel(plc.clone());
+            let last_error = self.last_error.clone();
+            match last_error.lock() {
+                Ok(mut guard) => *guard = None,
+                Err(poisoned) => *poisoned.into_inner() = None,
+            }
+            *guard = Some(match self.max_restarts {
+                Some(max_restarts) => {
+                    let metrics = self.metrics.clone();
+                    std::thread::spawn(move || supervise(plc, max_restarts, &metrics, &last_error))
+                }
+                None => std::thread::spawn(move || run_clock_keeping_error(plc, &last_error)),
+            });
+            self.ensure_monitor();
+
+            #[cfg(feature = "tracing")]
+            tracing::info!("PhaseLockedClock started");
+        } else {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("PhaseLockedClock is already running");
+        }
+        Ok(())
+    }
+
+    /// Get the error that ended the clock thread, i.e. an exception from clockkit such as a
+    /// failing socket or a panic of a custom primary clock, as [`Error::ClockThread`]. `None`
+    /// while the thread runs or if it ended normally, and cleared when the clock is started
+    /// again. Failures that [`Config::auto_restart`] restarted from are only counted.
+    ///
+    /// This tells why a clock that never synchronizes has stopped trying; a thread that still
+    /// runs but can't reach its server shows up in [`Self::diagnostics`] instead, and a panic in
+    /// [`Self::join`].
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4486);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4486).build_clock();
+    /// assert!(clock.last_error().is_none());
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// clock.stop();
+    /// assert!(clock.last_error().is_none());
+    /// ```
+    pub fn last_error(&self) -> Option<Error> {
+        let last_error = match self.last_error.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        (*last_error).clone().map(Error::ClockThread)
+    }
+
+    /// Block until the PLC is synchronized, failing with [`Error::Timeout`] if that does not
+    /// happen within `timeout`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "DEBUG", skip(self)))]
+    pub fn wait_until_synchronized(&self, timeout: Duration) -> Result<(), Error> {
+        let start = Instant::now();
+        let mut backoff = Duration::from_millis(1);
+        while !self.is_synchronized() {
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Err(Error::Timeout);
+            }
+            std::thread::sleep(backoff.min(timeout - elapsed));
+            backoff = (backoff * 2).min(Duration::from_millis(50));
+        }
+        Ok(())
+    }
+
+    /// Start the clock, call `f` with its value every `interval` until `dur` has elapsed, then
+    /// stop it. Requires the `chrono` feature.
+    ///
+    /// Ticks while the clock is out of sync are skipped, any other error stops the clock and is
+    /// returned.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4452);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4452).build_clock();
+    /// let mut values = Vec::new();
+    /// clock
+    ///     .run_for(Duration::from_secs(2), Duration::from_millis(100), |v| values.push(v))
+    ///     .unwrap();
+    /// assert!(!values.is_empty());
+    /// assert!(!clock.is_running());
+    /// ```
+    #[cfg(feature = "chrono")]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "INFO", skip(self, f))
+    )]
+    pub fn run_for<F: FnMut(DateTime<Utc>)>(
+        &self,
+        dur: Duration,
+        interval: Duration,
+        mut f: F,
+    ) -> Result<(), Error> {
+        self.try_start()?;
+        let start = Instant::now();
+        let res = loop {
+            match self.get_value() {
+                Ok(value) => f(value),
+                // Transient, the clock may synchronize again.
+                Err(Error::OutOfSync) => {}
+                Err(e) => break Err(e),
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= dur {
+                break Ok(());
+            }
+            std::thread::sleep(interval.min(dur - elapsed));
+        };
+        self.stop();
+        res
+    }
+
+    /// Start the clock and sample its offset and round trip time every 100ms for `duration`, e.g.
+    /// to validate the link to a server before trusting the clock for data collection.
+    ///
+    /// The clock keeps running afterwards. Fails with [`Error::OutOfSync`] if it was out of sync
+    /// at every sample.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4482);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4482).build_clock();
+    /// let report = clock.measure(Duration::from_secs(1)).unwrap();
+    /// assert!(report.offsets.samples > 0);
+    /// assert!(report.updates > 0);
+    /// assert!(report.max_abs_offset() < 100_000);
+    /// assert!(report.mean_rtt <= report.max_rtt);
+    /// assert!(clock.is_running());
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip(self)))]
+    pub fn measure(&self, duration: Duration) -> Result<QualityReport, Error> {
+        self.try_start()?;
+        let before = self.diagnostics();
+        let mut updates = before.updates;
+        let mut offsets = VecDeque::new();
+        let mut rtts = Vec::new();
+        let mut out_of_sync = 0;
+        let start = Instant::now();
+        loop {
+            let elapsed = start.elapsed();
+            if elapsed >= duration {
+                break;
+            }
+            std::thread::sleep(MONITOR_INTERVAL.min(duration - elapsed));
+            match self.snapshot().offset {
+                Some(offset) => offsets.push_back(offset),
+                None => out_of_sync += 1,
+            }
+            let diagnostics = self.diagnostics();
+            if diagnostics.updates != updates {
+                updates = diagnostics.updates;
+                rtts.extend(diagnostics.last_rtt);
+            }
+        }
+        if offsets.is_empty() {
+            return Err(Error::OutOfSync);
+        }
+        let after = self.diagnostics();
+        let mean_rtt = match u32::try_from(rtts.len()) {
+            Ok(n) if n > 0 => rtts.iter().sum::<Duration>() / n,
+            _ => Duration::ZERO,
+        };
+        Ok(QualityReport {
+            offsets: OffsetStats::from_samples(&offsets),
+            out_of_sync,
+            mean_rtt,
+            max_rtt: rtts.iter().max().copied().unwrap_or_default(),
+            updates: after.updates.saturating_sub(before.updates),
+            timeouts: after.timeouts.saturating_sub(before.timeouts),
+        })
+    }
+
+    /// Start the monitor thread unless it is already running.
+    fn ensure_monitor(&self) {
+        let mut monitor = match self.monitor.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if (*monitor).is_none() {
+            *monitor = Some(Monitor::spawn(
+                self.ptr.clone(),
+                self.subscribers.clone(),
+                self.stats.clone(),
+                self.metrics.clone(),
+                self.callbacks.clone(),
+                self.watchers.clone(),
+                self.heartbeat.clone(),
+            ));
+        }
+    }
+
+    /// Register `f` to be called after each successful update from the server, e.g. to steer
+    /// another clock with the offset.
+    ///
+    /// `f` runs on the clock's monitor thread, which checks for updates every 100ms, so several
+    /// updates within that time are reported once. It must neither block nor panic, as that
+    /// stalls or ends the monitor, including [`Self::subscribe`] and [`Self::metrics`]. It may
+    /// register further callbacks, which are called from the next update on.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::{sync::mpsc, time::Duration};
+    /// let server = clockkit::Server::new(4469);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4469).build_clock();
+    /// let (tx, rx) = mpsc::channel();
+    /// clock.on_update(move |update| {
+    ///     let _ = tx.send(update);
+    /// });
+    /// clock.start();
+    /// let update = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    /// assert!(update.rtt > Duration::ZERO);
+    /// ```
+    pub fn on_update<F: Fn(Update) + Send + 'static>(&self, f: F) {
+        let f: Callback = Arc::new(Mutex::new(Box::new(f)));
+        match self.callbacks.lock() {
+            Ok(mut callbacks) => callbacks.push(f),
+            Err(poisoned) => poisoned.into_inner().push(f),
+        }
+    }
+
+    /// Get notified when the synchronization state changes.
+    ///
+    /// Only transitions are sent, starting with [`SyncEvent::Synchronized`] once the clock
+    /// first synchronizes. The receiver is disconnected when the clock is stopped or dropped.
+    /// Subscribing to a clock that is not running yet receives the events of the next run.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, SyncEvent};
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4449);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4449).build_clock();
+    /// let events = clock.subscribe();
+    /// clock.start();
+    /// assert_eq!(
+    ///     events.recv_timeout(Duration::from_secs(5)),
+    ///     Ok(SyncEvent::Synchronized)
+    /// );
+    /// clock.stop();
+    /// // Events sent before stopping are still delivered, then the iterator ends.
+    /// for _ in events.iter() {}
+    /// ```
+    pub fn subscribe(&self) -> mpsc::Receiver<SyncEvent> {
+        let (tx, rx) = mpsc::channel();
+        match self.subscribers.lock() {
+            Ok(mut subs) => subs.push(tx),
+            Err(poisoned) => poisoned.into_inner().push(tx),
+        }
+        rx
+    }
+
+    /// Start sampling the offset while synchronized, every 100ms for a window of at most
+    /// 1000 samples, see [`Self::offset_stats`].
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4454);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4454).build_clock();
+    /// clock.enable_offset_stats();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(500));
+    /// clock.stop();
+    ///
+    /// let stats = clock.offset_stats();
+    /// assert!(stats.samples > 0);
+    /// assert!(stats.min as f64 <= stats.mean && stats.mean <= stats.max as f64);
+    /// clock.reset_stats();
+    /// assert_eq!(clock.offset_stats().samples, 0);
+    /// ```
+    pub fn enable_offset_stats(&self) {
+        match self.stats.lock() {
+            Ok(mut stats) => {
+                stats.get_or_insert_with(|| VecDeque::with_capacity(STATS_WINDOW));
+            }
+            Err(poisoned) => {
+                poisoned
+                    .into_inner()
+                    .get_or_insert_with(|| VecDeque::with_capacity(STATS_WINDOW));
+            }
+        }
+    }
+
+    /// Get the statistics of the sampled offsets, empty unless enabled with
+    /// [`Self::enable_offset_stats`].
+    pub fn offset_stats(&self) -> OffsetStats {
+        let stats = match self.stats.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        (*stats)
+            .as_ref()
+            .map(OffsetStats::from_samples)
+            .unwrap_or_default()
+    }
+
+    /// Discard the sampled offsets, sampling continues if enabled.
+    pub fn reset_stats(&self) {
+        let mut stats = match self.stats.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some(samples) = (*stats).as_mut() {
+            samples.clear();
+        }
+    }
+
+    /// Get the counters of the requests to the server, e.g. to tell an unreachable server from
+    /// jitter exceeding the phase panic when the clock doesn't synchronize.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4456);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4456).build_clock();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(500));
+    /// let diagnostics = clock.diagnostics();
+    /// assert!(diagnostics.updates > 0);
+    /// assert!(diagnostics.last_rtt.is_some());
+    /// assert!(diagnostics.last_update.is_some());
+    ///
+    /// // Nothing is listening on this port.
+    /// let clock = clockkit::Config::default().port(4457).build_clock();
+    /// clock.start();
+    /// std::thread::sleep(Duration::from_millis(500));
+    /// let diagnostics = clock.diagnostics();
+    /// assert_eq!(diagnostics.updates, 0);
+    /// assert!(diagnostics.timeouts > 0);
+    /// assert_eq!(diagnostics.last_update, None);
+    /// ```
+    pub fn diagnostics(&self) -> Diagnostics {
+        ffi::getDiagnostics(self.ptr.clone()).into()
+    }
+
+    /// Discard the phase and frequency estimates, so that the clock resynchronizes from scratch,
+    /// e.g. after a long outage.
+    ///
+    /// [`Self::is_synchronized`] returns `false` right away, until the clock thread has locked
+    /// to the server again with its next update. The thresholds and counters are kept.
+    ///
+    /// As only the clock thread can resynchronize, this fails if it isn't running: with
+    /// [`Error::ClockThread`] if it ended with an error, see [`Self::last_error`], or else with
+    /// [`Error::OutOfSync`].
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4471);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4471).build_clock();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// clock.reset().unwrap();
+    /// assert!(!clock.is_synchronized());
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    ///
+    /// clock.stop();
+    /// assert!(matches!(clock.reset(), Err(clockkit::Error::OutOfSync)));
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub fn reset(&self) -> Result<(), Error> {
+        if !self.is_running() {
+            return Err(self.last_error().unwrap_or(Error::OutOfSync));
+        }
+        ffi::reset(self.ptr.clone());
+        Ok(())
+    }
+
+    /// Stop querying the server while keeping the clock thread alive, e.g. during an operation
+    /// known to disrupt the network, until [`Self::resume`].
+    ///
+    /// While paused the clock isn't disciplined, [`Self::get_value`] keeps interpolating from the
+    /// last update at the last estimated frequency, and the clock keeps reporting to be
+    /// synchronized. The update panic is only checked again on resume: a pause longer than
+    /// `updatePanic` makes the clock go out of sync with the first update after it, and lock to
+    /// the server from scratch. The pause is kept across [`Self::stop`] and [`Self::start`].
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4484);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4484).build_clock();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(500));
+    ///
+    /// clock.pause();
+    /// assert!(clock.is_paused());
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// let updates = clock.diagnostics().updates;
+    /// std::thread::sleep(Duration::from_millis(600));
+    /// assert_eq!(clock.diagnostics().updates, updates);
+    /// assert!(clock.get_value().is_ok());
+    /// assert!(clock.is_running());
+    ///
+    /// clock.resume();
+    /// std::thread::sleep(Duration::from_millis(600));
+    /// assert!(clock.diagnostics().updates > updates);
+    /// ```
+    pub fn pause(&self) {
+        ffi::setPaused(self.ptr.clone(), true);
+        #[cfg(feature = "tracing")]
+        tracing::info!("PhaseLockedClock paused");
+    }
+
+    /// Query the server again after [`Self::pause`], from the next update on.
+    pub fn resume(&self) {
+        ffi::setPaused(self.ptr.clone(), false);
+        #[cfg(feature = "tracing")]
+        tracing::info!("PhaseLockedClock resumed");
+    }
+
+    /// Check whether the clock is paused, see [`Self::pause`].
+    pub fn is_paused(&self) -> bool {
+        ffi::isPaused(&self.ptr)
+    }
+
+    /// Get the round trip time of the last successful request to the server.
+    ///
+    /// Fails with [`Error::OutOfSync`] before the first successful request. See
+    /// [`Self::diagnostics`] for the RTT together with the request counters.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Error};
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4472);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4472).build_clock();
+    /// assert!(matches!(clock.last_rtt(), Err(Error::OutOfSync)));
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(500));
+    /// assert!(clock.last_rtt().unwrap() < Duration::from_secs(1));
+    /// ```
+    pub fn last_rtt(&self) -> Result<Duration, Error> {
+        match ffi::getLastRTT(&self.ptr) {
+            USEC_INVALID => Err(Error::OutOfSync),
+            usec => Ok(make_duration(usec)),
+        }
+    }
+
+    /// Get the sequence number of the last packet received from the server, `None` before the
+    /// first one.
+    ///
+    /// Requests are numbered from 1 to 249, wrapping to 0 after that, and each reply carries the
+    /// number of its request. Replies arriving too late for their request are included, so a
+    /// number lagging behind the requests sent points to a slow or reordering link, while
+    /// [`Diagnostics::timeouts`] growing without it moving points to lost packets.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4483);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4483).build_clock();
+    /// assert_eq!(clock.last_sequence(), None);
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(500));
+    /// assert!(clock.last_sequence().unwrap() < 250);
+    /// ```
+    pub fn last_sequence(&self) -> Option<u32> {
+        Some(ffi::getLastSequence(self.ptr.clone())).filter(|&seq| seq != u32::MAX)
+    }
+
+    /// Get the time since the last successful update from the server, as measured by the
+    /// primary clock.
+    ///
+    /// Unlike [`Self::is_synchronized`], which only turns `false` once the update panic is
+    /// exceeded, this allows to watch for a stalled clock with a threshold of one's own. Fails
+    /// with [`Error::OutOfSync`] before the first successful update.
+    ///
+    /// This reads a custom primary clock on the calling thread, so a panic of it aborts the
+    /// process, see [`Config::try_build_clock_with_primary`].
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Error};
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4465);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4465).build_clock();
+    /// assert!(matches!(clock.sync_age(), Err(Error::OutOfSync)));
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(500));
+    /// assert!(clock.sync_age().unwrap() < Duration::from_secs(1));
+    /// ```
+    pub fn sync_age(&self) -> Result<Duration, Error> {
+        match ffi::getSyncAge(&self.ptr) {
+            USEC_INVALID => Err(Error::OutOfSync),
+            usec => Ok(make_duration(usec)),
+        }
+    }
+
+    /// Get the counters of the clock, which can be read without locks or calls into C++.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4462);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4462).build_clock();
+    /// let metrics = clock.metrics();
+    /// assert_eq!(metrics.updates(), 0);
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(500));
+    /// assert!(metrics.updates() > 0);
+    /// ```
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Get a cheap, cloneable handle for reading the clock from other threads, see
+    /// [`ClockHandle`].
+    pub fn handle(&self) -> ClockHandle {
+        ClockHandle {
+            ptr: self.ptr.clone(),
+        }
+    }
+
+    /// Check whether the PLC thread is running.
+    ///
+    /// After [`Self::stop`] this becomes `false` as soon as the thread has returned.
+    pub fn is_running(&self) -> bool {
+        let guard = match self.handle.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        (*guard).as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    /// Stop the PLC and wait for its thread to finish, so that it can be started again.
+    ///
+    /// A panic of the clock thread is discarded, use [`Self::join`] to observe it.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4446);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4446).build_clock();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// clock.stop();
+    /// assert!(!clock.is_running());
+    ///
+    /// clock.start();
+    /// assert!(clock.is_running());
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// clock.get_value().expect("Failed to get value after restart");
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub fn stop(&self) {
+        if self.join().is_err() {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("PhaseLockedClock thread panicked");
+        }
+    }
+
+    /// Stop the PLC and wait for its thread to finish.
+    ///
+    /// Returns the result of the clock thread, i.e. `Err` if it panicked. Calling `join()` on a
+    /// clock that is not running is a no-op returning `Ok(())`. Unlike dropping the clock, which
+    /// gives up after a timeout, this waits for as long as the thread takes to return.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub fn join(&self) -> std::thread::Result<()> {
+        match self.cancel() {
+            Some(h) => h.join(),
+            None => Ok(()),
+        }
+    }
+
+    /// Stop the PLC, wait for its thread and report how it ended.
+    ///
+    /// Fails with
+    /// - [`Error::ThreadPanicked`] if the clock thread panicked,
+    /// - [`Error::ClockThread`] if it ended with an error, see [`Self::last_error`],
+    /// - or [`Error::OutOfSync`] if the clock was running but out of sync when shut down.
+    ///
+    /// A clock that was never started shuts down with `Ok(())`. As this consumes the clock, dropping it has
+    /// nothing left to do; use [`Self::stop`] or [`Self::join`] to stop a borrowed clock.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Error};
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4461);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4461).build_clock();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// clock.shutdown().unwrap();
+    ///
+    /// // Nothing listens on this port, so the clock never synchronizes.
+    /// let clock = clockkit::Config::default().port(4453).build_clock();
+    /// clock.start();
+    /// assert!(matches!(clock.shutdown(), Err(Error::OutOfSync)));
+    /// ```
+    ///
+    /// A clock thread ended by an error:
+    /// ```
+    /// # use clockkit::{self, Error, SystemClock};
+    /// # use std::sync::atomic::{AtomicBool, Ordering};
+    /// # use std::time::{Duration, Instant};
+    /// let server = clockkit::Server::new(4487);
+    /// server.start();
+    ///
+    /// static FAIL: AtomicBool = AtomicBool::new(false);
+    /// let primary = || {
+    ///     assert!(!FAIL.load(Ordering::SeqCst), "hardware clock unavailable");
+    ///     SystemClock::now()
+    /// };
+    /// let clock = clockkit::Config::default()
+    ///     .port(4487)
+    ///     .try_build_clock_with_primary(Box::new(primary))
+    ///     .unwrap();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    ///
+    /// // The panic of the primary clock ends the clock thread.
+    /// FAIL.store(true, Ordering::SeqCst);
+    /// let start = Instant::now();
+    /// while clock.is_running() {
+    ///     assert!(start.elapsed() < Duration::from_secs(5));
+    ///     std::thread::sleep(Duration::from_millis(10));
+    /// }
+    /// let Err(Error::ClockThread(message)) = clock.shutdown() else {
+    ///     panic!("expected the clock thread's error");
+    /// };
+    /// assert!(message.contains("hardware clock unavailable"));
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub fn shutdown(self) -> Result<(), Error> {
+        let running = self.is_running();
+        let synchronized = self.is_synchronized();
+        self.join().map_err(|_| Error::ThreadPanicked)?;
+        if let Some(error) = self.last_error() {
+            return Err(error);
+        }
+        if running && !synchronized {
+            return Err(Error::OutOfSync);
+        }
+        Ok(())
+    }
+
+    /// Stop the monitor and tell the clock thread to return, handing out its handle if running.
+    fn cancel(&self) -> Option<JoinHandle<()>> {
+        let handle = match self.handle.lock() {
+            Ok(mut guard) => (*guard).take(),
+            Err(poisoned) => poisoned.into_inner().take(),
+        };
+        let monitor = match self.monitor.lock() {
+            Ok(mut guard) => (*guard).take(),
+            Err(poisoned) => poisoned.into_inner().take(),
+        };
+        if let Some(m) = monitor {
+            m.stop();
+        }
+        if handle.is_some() {
+            ffi::cancel(self.ptr.clone());
+        }
+        handle
+    }
+
+    /// Set the threshold for the phase panic.
+    ///
+    /// phasePanic: A PhaseLockedClock whose offset exceeds this,
+    /// relative to its reference clock, declares itself out of sync.
+    /// Default: 5ms
+    ///
+    /// Fails with [`Error::Overflow`] if `dur` doesn't fit into i64 μs, leaving the threshold
+    /// unchanged.
+    ///
+    /// The threshold is stored atomically on the C++ side, so it can be changed on a running clock
+    /// shared between threads; the next update uses the new value.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Error};
+    /// # use std::{sync::Arc, time::Duration};
+    /// let clock = Arc::new(clockkit::Config::default().build_clock());
+    /// clock.start();
+    /// let shared = Arc::clone(&clock);
+    /// std::thread::spawn(move || shared.set_phase_panic(Duration::from_millis(5)))
+    ///     .join()
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert_eq!(clock.phase_panic(), Duration::from_millis(5));
+    /// assert!(matches!(clock.set_phase_panic(Duration::MAX), Err(Error::Overflow)));
+    /// assert_eq!(clock.phase_panic(), Duration::from_millis(5));
+    /// clock.stop();
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub fn set_phase_panic(&self, dur: Duration) -> Result<(), Error> {
+        let dur = dur.as_micros().try_into().map_err(|_| Error::Overflow)?;
+        let plc = self.ptr.clone();
+        ffi::setPhasePanic(plc, dur);
+        Ok(())
+    }
+
+    /// Set the threshold for the update panic.
+    ///
+    /// updatePanic: A PhaseLockedClock that hasn't updated successfully
+    /// for longer than this declares itself out of sync.
+    /// Default: 5s
+    ///
+    /// Fails with [`Error::Overflow`] if `dur` doesn't fit into i64 μs, leaving the threshold
+    /// unchanged.
+    ///
+    /// Like the phase panic, this can be changed on a running clock shared between threads.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Error};
+    /// # use std::time::Duration;
+    /// let clock = clockkit::Config::default().build_clock();
+    /// clock.set_update_panic(Duration::from_millis(5)).unwrap();
+    /// assert_eq!(clock.update_panic(), Duration::from_millis(5));
+    /// assert!(matches!(clock.set_update_panic(Duration::MAX), Err(Error::Overflow)));
+    /// assert_eq!(clock.update_panic(), Duration::from_millis(5));
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub fn set_update_panic(&self, dur: Duration) -> Result<(), Error> {
+        let dur = dur.as_micros().try_into().map_err(|_| Error::Overflow)?;
+        let plc = self.ptr.clone();
+        ffi::setUpdatePanic(plc, dur);
+        Ok(())
+    }
+
+    /// Set how long a request to the server may take, see [`Config::timeout()`].
+    ///
+    /// Fails with [`Error::Overflow`] if `dur` doesn't fit into the `u32` μs of the config, and
+    /// with [`Error::ConfigInvalid`] if it is zero, leaving the timeout unchanged. Like the panic
+    /// thresholds, this can be changed on a running clock; the next request uses the new value.
+    ///
+    /// Keep the timeout well below the [update panic](Self::set_update_panic): failed requests
+    /// count as missed updates, so a clock whose requests take longer than the update panic goes
+    /// out of sync before a reply can arrive. Unlike [`Config::validate`] this is not checked
+    /// here, so that both can be changed one after the other.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, Error};
+    /// # use std::time::Duration;
+    /// let clock = clockkit::Config::default().build_clock();
+    /// assert_eq!(clock.timeout(), Duration::from_millis(1));
+    /// clock.set_timeout(Duration::from_millis(20)).unwrap();
+    /// assert_eq!(clock.timeout(), Duration::from_millis(20));
+    /// assert!(matches!(clock.set_timeout(Duration::from_secs(5000)), Err(Error::Overflow)));
+    /// assert!(matches!(clock.set_timeout(Duration::ZERO), Err(Error::ConfigInvalid { .. })));
+    /// assert_eq!(clock.timeout(), Duration::from_millis(20));
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
+    pub fn set_timeout(&self, dur: Duration) -> Result<(), Error> {
+        let dur: u32 = dur.as_micros().try_into().map_err(|_| Error::Overflow)?;
+        if dur == 0 {
+            return Err(Error::ConfigInvalid {
+                key: "timeout".to_string(),
+                reason: "must not be 0",
+            });
+        }
+        ffi::setTimeout(self.ptr.clone(), dur.into());
+        Ok(())
+    }
+
+    /// Get the current request timeout, see [`Self::set_timeout`].
+    pub fn timeout(&self) -> Duration {
+        make_duration(ffi::getTimeout(self.ptr.clone()))
+    }
+
+    /// Get the current threshold for the phase panic, see [`Self::set_phase_panic`].
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let clock = clockkit::Config::default().phase_panic(5000).build_clock();
+    /// assert_eq!(clock.phase_panic(), Duration::from_millis(5));
+    /// clock.set_phase_panic(Duration::from_millis(10)).unwrap();
+    /// assert_eq!(clock.phase_panic(), Duration::from_millis(10));
+    /// ```
+    pub fn phase_panic(&self) -> Duration {
+        make_duration(ffi::getPhasePanic(self.ptr.clone()))
+    }
+
+    /// Get the current threshold for the update panic, see [`Self::set_update_panic`].
+    pub fn update_panic(&self) -> Duration {
+        make_duration(ffi::getUpdatePanic(self.ptr.clone()))
+    }
+
+    /// Iterate over the clock's values every `interval`, requires the `chrono` feature.
+    ///
+    /// The first value is fetched immediately. The iterator ends once the clock is stopped, so
+    /// start it beforehand.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// # use std::time::Duration;
+    /// let server = clockkit::Server::new(4455);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4455).build_clock();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// let values = clock
+    ///     .iter(Duration::from_millis(10))
+    ///     .take(3)
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .unwrap();
+    /// assert!(values[0] <= values[2]);
+    ///
+    /// clock.stop();
+    /// assert!(clock.iter(Duration::from_millis(10)).next().is_none());
+    /// ```
+    #[cfg(feature = "chrono")]
+    pub fn iter(&self, interval: Duration) -> TimestampIter<'_> {
+        TimestampIter {
+            clock: self,
+            interval,
+            first: true,
+        }
+    }
+}
+
+/// Blocking iterator over the values of a [`PhaseLockedClock`], see [`PhaseLockedClock::iter`].
+#[cfg(feature = "chrono")]
+pub struct TimestampIter<'a> {
+    clock: &'a PhaseLockedClock,
+    interval: Duration,
+    first: bool,
+}
+
+#[cfg(feature = "chrono")]
+impl Iterator for TimestampIter<'_> {
+    type Item = Result<DateTime<Utc>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !std::mem::take(&mut self.first) {
+            std::thread::sleep(self.interval);
+        }
+        if !self.clock.is_running() {
+            return None;
+        }
+        Some(self.clock.get_value())
+    }
+}
+
+/// A client querying a clockkit server directly.
+///
+/// Every [`query`](Self::query) does a single round trip to the server. This is meant for
+/// diagnostics, e.g. checking that a server is reachable, and does not provide the latency and
+/// jitter guarantees of a [`PhaseLockedClock`].
+///
+/// Example:
+/// ```no_run
+/// # use clockkit;
+/// let client = clockkit::ClockClient::new("127.0.0.1".to_string(), 4444, 1000);
+/// let ts = client.query().expect("Failed to query clockkit server");
 /// ```
-pub struct PhaseLockedClock {
-    ptr: SharedPtr<ffi::PhaseLockedClock>,
-    handle: Mutex<Option<JoinHandle<()>>>,
+pub struct ClockClient {
+    ptr: SharedPtr<ffi::ClockClient>,
 }
 
-/// Helper function to create a NaiveDateTime from a timestamp in μs.
-#[cfg_attr(feature = "tracing", tracing::instrument(level = "DEBUG"))]
-fn make_timestamp(usec: i64) -> Result<NaiveDateTime, Error> {
-    if usec == USEC_INVALID {
-        return Err(Error::Invalid(usec));
+impl ClockClient {
+    /// Create a client for the server at `server:port`.
+    ///
+    /// `timeout` is the time in μs to wait for a reply.
+    pub fn new(server: String, port: u16, timeout: u32) -> Self {
+        Self::with_bind_address(server, port, timeout, String::new())
+    }
+
+    /// Create a client sending from the local IP address `bind_address`, see
+    /// [`Config::bind_address`]. An empty address keeps the OS's default routing.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// let server = clockkit::Server::new(4466);
+    /// server.start();
+    /// # std::thread::sleep(std::time::Duration::from_millis(50));
+    ///
+    /// let client = clockkit::ClockClient::with_bind_address("127.0.0.1", 4466, 100000, "127.0.0.1");
+    /// client.query_raw().unwrap();
+    /// ```
+    pub fn with_bind_address(
+        server: impl Into<String>,
+        port: u16,
+        timeout: u32,
+        bind_address: impl Into<String>,
+    ) -> Self {
+        Self {
+            ptr: ffi::buildClient(server.into(), port, timeout, bind_address.into()),
+        }
+    }
+
+    /// Ask the server for its current time, requires the `chrono` feature.
+    ///
+    /// Fails with [`Error::Timeout`] if no valid reply arrived within the timeout.
+    #[cfg(feature = "chrono")]
+    pub fn query(&self) -> Result<DateTime<Utc>, Error> {
+        Timestamp::from_micros(self.query_raw()?).to_datetime()
+    }
+
+    /// Ask the server for its current time and return its reply packet, for protocol debugging.
+    ///
+    /// Fails with [`Error::Timeout`] if no valid reply arrived within the timeout.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, PacketType};
+    /// let server = clockkit::Server::new(4451);
+    /// server.start();
+    /// # std::thread::sleep(std::time::Duration::from_millis(50));
+    ///
+    /// let client = clockkit::ClockClient::new("127.0.0.1".to_string(), 4451, 100000);
+    /// let packet = client.query_packet().unwrap();
+    /// assert_eq!(packet.kind(), PacketType::Reply);
+    /// assert!(packet.rtt() >= 0);
+    /// assert!(packet.client_receive_time() >= packet.client_request_time());
+    /// ```
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "DEBUG", skip_all))]
+    pub fn query_packet(&self) -> Result<ClockPacket, Error> {
+        let packet = ClockPacket(ffi::queryClientPacket(self.ptr.clone()));
+        match packet.kind() {
+            PacketType::Invalid => Err(Error::Timeout),
+            _ => Ok(packet),
+        }
+    }
+
+    /// Ask the server for its current time as μs since the Unix epoch.
+    ///
+    /// Fails with [`Error::Timeout`] if no valid reply arrived within the timeout.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "DEBUG", skip_all))]
+    pub fn query_raw(&self) -> Result<i64, Error> {
+        match ffi::getClientValue(self.ptr.clone()) {
+            USEC_INVALID => Err(Error::Timeout),
+            usec => Ok(usec),
+        }
     }
-    let sec: i64 = usec / 1_000_000;
-    let usec: Result<u32, _> = match (usec % 1_000_000).try_into() {
-        Ok(x) => Ok(x),
-        Err(_) => Err(Error::Overflow),
-    };
-    usec.and_then(|us| {
-        NaiveDateTime::from_timestamp_opt(sec, us * 1000) // second value are nanoseconds
-            .ok_or(Error::Overflow)
-    })
 }
 
-impl PhaseLockedClock {
-    pub fn get_value(&self) -> Result<chrono::DateTime<Utc>, Error> {
-        make_timestamp(ffi::getValue(self.ptr.clone())).map(|ts| DateTime::<Utc>::from_utc(ts, Utc))
+/// The local system clock, which is the primary clock of a [`PhaseLockedClock`].
+///
+/// It uses the same units as the rest of clockkit, μs since the Unix epoch, so its values can be
+/// compared directly to e.g. [`PhaseLockedClock::get_value_raw`].
+///
+/// Example:
+/// ```
+/// # use clockkit::SystemClock;
+/// let start = SystemClock::now();
+/// assert!(SystemClock::now() >= start);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// Get the current time in μs since the Unix epoch.
+    pub fn now() -> i64 {
+        ffi::getSystemValue()
     }
+}
 
-    /// Check whether the PLC is synchronized.
-    pub fn is_synchronized(&self) -> bool {
-        self.ptr.isSynchronized()
+/// A local clock which a [`PhaseLockedClock`] locks to the server in place of the
+/// [`SystemClock`], see [`Config::try_build_clock_with_primary`].
+///
+/// Implemented for closures returning the ticks.
+pub trait PrimaryClock: Send + Sync {
+    /// The current value in ticks of [`Config::primary_frequency_hz`], by default 1 MHz, i.e. μs.
+    fn value_ticks(&self) -> i64;
+}
+
+impl<F: Fn() -> i64 + Send + Sync> PrimaryClock for F {
+    fn value_ticks(&self) -> i64 {
+        self()
     }
+}
 
-    /// Run the PLC in its own thread
+/// A [`PrimaryClock`] handed to C++.
+struct PrimaryClockBox {
+    clock: Box<dyn PrimaryClock>,
+    frequency_hz: u32,
+}
+
+/// The value of the primary clock in μs, as clockkit expects.
+///
+/// A panic of the clock is thrown on as a C++ exception, which ends a clock thread with an error
+/// instead of aborting at the FFI boundary.
+fn primary_value(clock: &PrimaryClockBox) -> Result<i64, String> {
+    let ticks = std::panic::catch_unwind(AssertUnwindSafe(|| clock.clock.value_ticks()))
+        .map_err(|panic| format!("primary clock panicked: {}", panic_message(&*panic)))?;
+    if clock.frequency_hz == 1_000_000 {
+        return Ok(ticks);
+    }
+    let usec = i128::from(ticks) * 1_000_000 / i128::from(clock.frequency_hz);
+    Ok(usec.try_into().unwrap_or(USEC_INVALID))
+}
+
+/// The message of a panic, if it has one.
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => panic.downcast_ref::<String>().map_or("", String::as_str),
+    }
+}
+
+/// A packet trace handed to C++, see the `packet_trace` feature.
+struct PacketTraceBox(Box<dyn Fn(bool, ffi::PacketData) + Send + Sync>);
+
+/// Pass a packet sent (`true`) or received by a client to its trace.
+fn trace_packet(trace: &PacketTraceBox, sent: bool, packet: ffi::PacketData) {
+    (trace.0)(sent, packet);
+}
+
+impl Clock for SystemClock {
+    fn get_timestamp(&self) -> Result<Timestamp, Error> {
+        Ok(Timestamp::from_micros(SystemClock::now()))
+    }
+
+    /// Always `true`, the system clock is its own reference.
+    fn is_synchronized(&self) -> bool {
+        true
+    }
+}
+
+impl Clock for ClockClient {
+    /// Query the server, see [`ClockClient::query_raw`].
+    fn get_timestamp(&self) -> Result<Timestamp, Error> {
+        self.query_raw().map(Timestamp::from_micros)
+    }
+
+    /// Whether the server answers a query, this is a round trip to the server.
+    fn is_synchronized(&self) -> bool {
+        self.query_raw().is_ok()
+    }
+}
+
+/// Frequency of all clocks apart from a [`VariableFrequencyClock`].
+const NOMINAL_FREQUENCY: f64 = 1_000_000.0;
+
+/// A clock running at an adjustable frequency relative to the system clock.
+///
+/// This is the clock a [`PhaseLockedClock`] steers to follow its reference. Its value starts at 0
+/// when created and advances by `frequency` ticks per second of the system clock, so at the
+/// nominal 1000000 Hz one tick is 1 μs.
+///
+/// Example:
+/// ```
+/// # use clockkit;
+/// let mut clock = clockkit::VariableFrequencyClock::new();
+/// assert_eq!(clock.rate(), 1.0);
+/// clock.set_master_frequency(2_000_000);
+/// assert_eq!(clock.rate(), 2.0);
+/// assert!(clock.value().unwrap() >= 0);
+/// ```
+pub struct VariableFrequencyClock {
+    ptr: SharedPtr<ffi::VariableFrequencyClock>,
+}
+
+impl VariableFrequencyClock {
+    /// Create a clock running at the nominal 1000000 Hz.
+    pub fn new() -> Self {
+        Self {
+            ptr: ffi::buildVariableClock(),
+        }
+    }
+
+    /// Get the current value in ticks.
+    ///
+    /// Fails with [`Error::Invalid`] if the system clock moved backwards.
+    pub fn value(&self) -> Result<i64, Error> {
+        match ffi::getVariableValue(self.ptr.clone()) {
+            USEC_INVALID => Err(Error::Invalid(USEC_INVALID)),
+            ticks => Ok(ticks),
+        }
+    }
+
+    /// Set the frequency in Hz; `0` is ignored.
+    pub fn set_master_frequency(&mut self, hz: u32) {
+        ffi::setVariableFrequency(self.ptr.clone(), hz.into())
+    }
+
+    /// The current rate relative to the nominal frequency, e.g. `2.0` running twice as fast.
+    pub fn rate(&self) -> f64 {
+        ffi::getVariableFrequency(self.ptr.clone()) / NOMINAL_FREQUENCY
+    }
+}
+
+impl Default for VariableFrequencyClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The type of a [`ClockPacket`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketType {
+    /// Uninitialized, or the query failed.
+    Invalid,
+    /// Client requesting the current time.
+    Request,
+    /// Server replying with its current time.
+    Reply,
+    /// Client reporting the status of its synchronization.
+    Acknowledge,
+    /// Terminate client and server.
+    Kill,
+    /// Unknown type byte.
+    Corrupt(u8),
+}
+
+impl From<u8> for PacketType {
+    fn from(kind: u8) -> Self {
+        match kind {
+            0 => Self::Invalid,
+            1 => Self::Request,
+            2 => Self::Reply,
+            3 => Self::Acknowledge,
+            4 => Self::Kill,
+            other => Self::Corrupt(other),
+        }
+    }
+}
+
+/// Read-only view of a clockkit protocol packet, see [`ClockClient::query_packet`].
+///
+/// All times are in μs since the Unix epoch, the client times from the local system clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClockPacket(ffi::PacketData);
+
+impl ClockPacket {
+    /// The packet type.
+    pub fn kind(&self) -> PacketType {
+        self.0.kind.into()
+    }
+
+    /// The sequence number, used to detect out-of-order packets.
+    pub fn sequence(&self) -> u8 {
+        self.0.sequence
+    }
+
+    /// When the client sent the request.
+    pub fn client_request_time(&self) -> i64 {
+        self.0.clientRequestTime
+    }
+
+    /// When the server got the request.
+    pub fn server_reply_time(&self) -> i64 {
+        self.0.serverReplyTime
+    }
+
+    /// When the client got the reply.
+    pub fn client_receive_time(&self) -> i64 {
+        self.0.clientReceiveTime
+    }
+
+    /// Round trip time in μs.
+    pub fn rtt(&self) -> i64 {
+        self.client_receive_time() - self.client_request_time()
+    }
+
+    /// Estimated offset of the server clock relative to the client clock in μs, assuming
+    /// symmetric network delays.
+    pub fn offset(&self) -> i64 {
+        self.server_reply_time() - self.client_receive_time() + self.rtt() / 2
+    }
+}
+
+/// A clockkit server, serving the local system time as reference to clients, or the time of a
+/// custom source, see [`Server::new_with_source`].
+///
+/// The server listens on all local addresses and runs in its own thread once started.
+///
+/// Example:
+/// ```
+/// # use clockkit;
+/// # use std::time::Duration;
+/// let server = clockkit::Server::new(4445);
+/// server.start();
+///
+/// let clock = clockkit::Config::default().port(4445).build_clock();
+/// clock.start();
+/// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+/// clock.get_value().expect("Failed to get value from in-process server");
+/// clock.join().unwrap();
+/// server.stop();
+/// ```
+pub struct Server {
+    ptr: SharedPtr<ffi::ClockServer>,
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Server {
+    /// Create a server for `port`; it only binds the port once started.
+    pub fn new(port: u16) -> Self {
+        Self {
+            ptr: ffi::buildServer(port),
+            handle: Mutex::new(None),
+        }
+    }
+
+    /// Create a server for `port` handing out the μs from `source` instead of the system time,
+    /// e.g. a synthetic time that jumps or drifts to test how clients handle that.
+    ///
+    /// `source` is called from the server thread for every request; a panic in it stops the
+    /// server.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, SystemClock};
+    /// # use std::sync::atomic::{AtomicI64, Ordering};
+    /// # use std::time::{Duration, Instant};
+    /// static SHIFT: AtomicI64 = AtomicI64::new(0);
+    /// let source = || SystemClock::now() + SHIFT.load(Ordering::Relaxed);
+    /// let server = clockkit::Server::new_with_source(4485, Box::new(source));
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default()
+    ///     .port(4485)
+    ///     .update_interval(500_000)
+    ///     .build_clock();
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    ///
+    /// // A jump far beyond the phase panic makes the clock go out of sync.
+    /// SHIFT.store(1_000_000, Ordering::Relaxed);
+    /// let start = Instant::now();
+    /// while clock.is_synchronized() {
+    ///     assert!(start.elapsed() < Duration::from_secs(5));
+    ///     std::thread::sleep(Duration::from_millis(10));
+    /// }
+    ///
+    /// // It then locks to the shifted time from scratch.
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// std::thread::sleep(Duration::from_millis(100));
+    /// let value = clock.get_value_raw().unwrap();
+    /// assert!((value - SystemClock::now() - 1_000_000).abs() < 100_000);
+    /// ```
+    pub fn new_with_source(port: u16, source: Box<dyn PrimaryClock>) -> Self {
+        let source = Box::new(PrimaryClockBox {
+            clock: source,
+            frequency_hz: 1_000_000,
+        });
+        Self {
+            ptr: ffi::buildServerWithSource(port, source),
+            handle: Mutex::new(None),
+        }
+    }
+
+    /// Run the server in its own thread.
+    ///
+    /// If the port can't be bound, e.g. because it is in use, the thread ends right away without
+    /// serving, which is logged with the `tracing` feature.
     #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
     pub fn start(&self) {
         if let Ok(mut guard) = self.handle.lock() {
-            // Only start the clock if there is no handle present, otherwise it's running.
+            // Only start the server if there is no handle present, otherwise it's running.
             if (*guard).is_none() {
-                let plc = self.ptr.clone();
-                *guard = Some(std::thread::spawn(move || ffi::run1(plc)));
+                let server = self.ptr.clone();
+                ffi::clearServerCancel(server.clone());
+                *guard = Some(std::thread::spawn(move || {
+                    if let Err(_e) = ffi::runServer(server) {
+                        #[cfg(feature = "tracing")]
+                        tracing::error!(error = %_e.what(), "Server thread failed");
+                    }
+                }));
 
                 #[cfg(feature = "tracing")]
-                tracing::info!("PhaseLockedClock started");
+                tracing::info!("Server started");
             } else {
                 #[cfg(feature = "tracing")]
-                tracing::warn!("PhaseLockedClock is already running");
+                tracing::warn!("Server is already running");
             }
         } else {
-            panic!("Unable to start PhaseLockedClock due to poisened mutex");
+            panic!("Unable to start Server due to poisened mutex");
         };
     }
 
-    /// Stop the PLC.
+    /// Stop the server and wait for its thread to finish, which takes at most 100 ms.
+    ///
+    /// Stopping right after [`Server::start`] is fine, even before the thread bound the port.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit;
+    /// let server = clockkit::Server::new(4488);
+    /// server.start();
+    /// server.stop();
+    ///
+    /// // The second server can't bind the port, which ends its thread without aborting.
+    /// let first = clockkit::Server::new(4488);
+    /// first.start();
+    /// let second = clockkit::Server::new(4488);
+    /// second.start();
+    /// std::thread::sleep(std::time::Duration::from_millis(100));
+    /// second.stop();
+    /// first.stop();
+    /// ```
     #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
     pub fn stop(&self) {
-        let plc = self.ptr.clone();
-        ffi::cancel(plc);
+        let handle = match self.handle.lock() {
+            Ok(mut guard) => (*guard).take(),
+            Err(poisoned) => poisoned.into_inner().take(),
+        };
+        if let Some(h) = handle {
+            ffi::cancelServer(self.ptr.clone());
+            // Also called from `drop`, so don't panic.
+            if h.join().is_err() {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Server thread panicked");
+            }
+        }
     }
+}
 
-    /// Set the threshold for the phase panic.
-    ///
-    /// phasePanic: A PhaseLockedClock whose offset exceeds this,
-    /// relative to its reference clock, declares itself out of sync.
-    /// Default: 5ms
-    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
-    pub fn set_phase_panic(&mut self, dur: Duration) {
-        let dur = dur
-            .as_micros()
-            .try_into()
-            .expect("Duration greater than i64");
-        let plc = self.ptr.clone();
-        ffi::setPhasePanic(plc, dur)
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop();
     }
+}
 
-    /// Set the threshold for the update panic.
-    ///
-    /// updatePanic: A PhaseLockedClock that hasn't updated successfully
-    /// for longer than this declares itself out of sync.
-    /// Default: 5s
-    #[cfg_attr(feature = "tracing", tracing::instrument(level = "INFO", skip_all))]
-    pub fn set_update_panic(&mut self, dur: Duration) {
-        let dur = dur
-            .as_micros()
-            .try_into()
-            .expect("Duration greater than i64");
-        let plc = self.ptr.clone();
-        ffi::setUpdatePanic(plc, dur)
+/// Shows the synchronization state and the current value.
+///
+/// Example:
+/// ```
+/// # use clockkit;
+/// let clock = clockkit::PhaseLockedClock::default();
+/// assert_eq!(
+///     clock.to_string(),
+///     "PhaseLockedClock { synced: false, value: out of sync }"
+/// );
+/// ```
+impl std::fmt::Display for PhaseLockedClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let synced = self.is_synchronized();
+        write!(f, "PhaseLockedClock {{ synced: {}, value: ", synced)?;
+        if !synced {
+            write!(f, "out of sync")?;
+        } else {
+            #[cfg(feature = "chrono")]
+            match self.get_value() {
+                Ok(ts) => write!(f, "{}", ts)?,
+                Err(e) => write!(f, "{}", e)?,
+            }
+            #[cfg(not(feature = "chrono"))]
+            match self.get_value_raw() {
+                Ok(usec) => write!(f, "{} μs", usec)?,
+                Err(e) => write!(f, "{}", e)?,
+            }
+        }
+        write!(f, " }}")
+    }
+}
+
+impl Clock for PhaseLockedClock {
+    fn get_timestamp(&self) -> Result<Timestamp, Error> {
+        PhaseLockedClock::get_timestamp(self)
+    }
+
+    fn is_synchronized(&self) -> bool {
+        PhaseLockedClock::is_synchronized(self)
+    }
+}
+
+/// A read-only view of a [`PhaseLockedClock`], see [`PhaseLockedClock::handle`].
+///
+/// Cloning it only copies a shared pointer to the C++ clock, so handles can be passed to as many
+/// threads as needed without an `Arc`. They read the clock but can't start or stop it; that stays
+/// with the owning [`PhaseLockedClock`]. A handle keeps the C++ clock alive after its owner is
+/// dropped, but as the owner stops the clock thread on drop the handle soon goes out of sync.
+///
+/// Example:
+/// ```
+/// # use clockkit;
+/// # use std::time::Duration;
+/// let server = clockkit::Server::new(4478);
+/// server.start();
+///
+/// let clock = clockkit::Config::default().port(4478).build_clock();
+/// clock.start();
+/// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+///
+/// let readers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let handle = clock.handle();
+///         std::thread::spawn(move || handle.get_value_raw())
+///     })
+///     .collect();
+/// for reader in readers {
+///     reader.join().unwrap().unwrap();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct ClockHandle {
+    ptr: SharedPtr<ffi::PhaseLockedClock>,
+}
+
+impl ClockHandle {
+    /// See [`PhaseLockedClock::is_synchronized`].
+    pub fn is_synchronized(&self) -> bool {
+        self.ptr.isSynchronized()
+    }
+
+    /// See [`PhaseLockedClock::get_value`], requires the `chrono` feature.
+    #[cfg(feature = "chrono")]
+    pub fn get_value(&self) -> Result<chrono::DateTime<Utc>, Error> {
+        self.get_timestamp()?.to_datetime()
+    }
+
+    /// See [`PhaseLockedClock::get_timestamp`].
+    pub fn get_timestamp(&self) -> Result<Timestamp, Error> {
+        self.get_value_raw().map(Timestamp::from_micros)
+    }
+
+    /// See [`PhaseLockedClock::get_value_raw`].
+    pub fn get_value_raw(&self) -> Result<i64, Error> {
+        match ffi::getValue(&self.ptr) {
+            USEC_INVALID => Err(Error::OutOfSync),
+            usec => Ok(usec),
+        }
+    }
+
+    /// See [`PhaseLockedClock::get_offset`].
+    pub fn get_offset(&self) -> Result<i64, Error> {
+        match ffi::getOffset(&self.ptr) {
+            USEC_INVALID => Err(Error::OutOfSync),
+            offset => Ok(offset),
+        }
+    }
+}
+
+impl Clock for ClockHandle {
+    fn get_timestamp(&self) -> Result<Timestamp, Error> {
+        ClockHandle::get_timestamp(self)
+    }
+
+    fn is_synchronized(&self) -> bool {
+        ClockHandle::is_synchronized(self)
+    }
+}
+
+impl Default for PhaseLockedClock {
+    /// Build a clock from the default [`Config`], equivalent to
+    /// `Config::default().build_clock()`, so it never panics: if the configured server doesn't
+    /// resolve or answer, the clock just never synchronizes.
+    fn default() -> Self {
+        Config::default().build_clock()
     }
 }
 
+/// How long dropping a running clock waits for its thread.
+const DROP_JOIN_TIMEOUT: Duration = Duration::from_secs(2);
+
 impl Drop for PhaseLockedClock {
-    /// `drop` tries to join the thread which will block and might panic.
+    /// `drop` stops the clock and waits up to 2s for its thread, which is detached if it does not
+    /// return in time. It never panics, a panic of the clock thread is discarded.
     ///
-    /// A detailed opinion on why waiting for `drop()` to join the thread might not be the best
-    /// option can be read here:
+    /// For a deterministic shutdown call [`Self::shutdown`] or [`Self::join`] instead, a detailed
+    /// opinion on why waiting for `drop()` to join the thread might not be the best option can be
+    /// read here:
     /// <https://stackoverflow.com/questions/41331577/joining-a-thread-in-a-method-that-takes-mut-self-like-drop-results-in-cann/42791007#42791007>
     fn drop(&mut self) {
-        if let Ok(mut guard) = self.handle.lock() {
-            if (*guard).is_some() {
-                self.stop();
-                match (*guard).take() {
-                    Some(h) => h.join(),
-                    None => Ok(()),
-                }
-                .expect("failed to join clock thread");
-            }
+        let Some(handle) = self.cancel() else {
+            return;
+        };
+        let start = Instant::now();
+        while !handle.is_finished() && start.elapsed() < DROP_JOIN_TIMEOUT {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        if !handle.is_finished() {
+            // The thread holds its own reference to the C++ clock, so it can outlive `self`.
+            #[cfg(feature = "tracing")]
+            tracing::warn!("PhaseLockedClock thread did not stop in time, detaching it");
+            return;
+        }
+        if handle.join().is_err() {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("PhaseLockedClock thread panicked");
         }
     }
 }
@@ -314,4 +4008,14 @@ impl Drop for PhaseLockedClock {
 /// many movements might end up causing the clock to go out of sync or result in inaccurate values.
 unsafe impl Send for ffi::PhaseLockedClock {}
 
+/// What the bridge reads or writes from other threads than the clock thread is atomic, i.e. the
+/// sync, cancel, pause and reset flags, the counters and the panic thresholds, or read under the
+/// clock's mutex, i.e. the phase and the variable frequency clock. The remaining state is only
+/// touched by the clock thread.
 unsafe impl Sync for ffi::PhaseLockedClock {}
+
+/// The server is only touched by its own thread once running; `cancelServer` and
+/// `clearServerCancel` just set an atomic flag.
+unsafe impl Send for ffi::ClockServer {}
+
+unsafe impl Sync for ffi::ClockServer {}
@@ -0,0 +1,140 @@
+//! Tracing of the raw packets a client exchanges with the server, requires the `packet_trace`
+//! feature.
+//!
+//! This is strictly a debugging aid for the protocol, not meant for production. Every packet
+//! calls back from C++ into Rust and runs the trace function on the clock thread, under a mutex
+//! the client takes for each packet. A slow trace, e.g. a blocking writer, delays the reply
+//! handling and thus inflates the measured round trip times and offsets.
+
+use crate::{ffi, ClockClient, ClockPacket, PacketTraceBox, PhaseLockedClock};
+use std::{fmt, io::Write, sync::Mutex};
+
+/// Whether a traced packet was sent or received by the client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketDirection {
+    Sent,
+    Received,
+}
+
+impl fmt::Display for PacketDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sent => "sent",
+            Self::Received => "received",
+        })
+    }
+}
+
+fn trace_box(
+    trace: impl Fn(PacketDirection, ClockPacket) + Send + Sync + 'static,
+) -> Box<PacketTraceBox> {
+    Box::new(PacketTraceBox(Box::new(move |sent, packet| {
+        let direction = if sent {
+            PacketDirection::Sent
+        } else {
+            PacketDirection::Received
+        };
+        trace(direction, ClockPacket(packet));
+    })))
+}
+
+/// Trace into `writer`, one line per packet, see [`PhaseLockedClock::trace_packets`].
+///
+/// Write errors are ignored, the trace is best effort.
+pub fn packet_writer(
+    writer: impl Write + Send + 'static,
+) -> impl Fn(PacketDirection, ClockPacket) + Send + Sync + 'static {
+    let writer = Mutex::new(writer);
+    move |direction, packet| {
+        let mut writer = match writer.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let _ = writeln!(
+            writer,
+            "{direction} {:?} seq={} request={} reply={} receive={}",
+            packet.kind(),
+            packet.sequence(),
+            packet.client_request_time(),
+            packet.server_reply_time(),
+            packet.client_receive_time()
+        );
+    }
+}
+
+/// Trace as `tracing` events at the TRACE level with the target `clockkit::packet`, within
+/// the span current on the clock thread. Requires the `tracing` feature as well.
+#[cfg(feature = "tracing")]
+pub fn log_packet(direction: PacketDirection, packet: ClockPacket) {
+    tracing::trace!(
+        target: "clockkit::packet",
+        %direction,
+        kind = ?packet.kind(),
+        sequence = packet.sequence(),
+        client_request_time = packet.client_request_time(),
+        server_reply_time = packet.server_reply_time(),
+        client_receive_time = packet.client_receive_time(),
+        "ClockPacket"
+    );
+}
+
+impl PhaseLockedClock {
+    /// Call `trace` with every packet the clock exchanges with the server, replacing an earlier
+    /// trace. Requires the `packet_trace` feature, see its [costs](crate::packet_trace).
+    ///
+    /// Received packets are traced before checking them, so out-of-order and late replies show
+    /// up too, with the local time they arrived at as receive time. Use [`packet_writer`] to log
+    /// into an [`std::io::Write`], or [`log_packet`] for `tracing`.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, PacketDirection, PacketType};
+    /// # use std::{sync::{Arc, Mutex}, time::Duration};
+    /// let server = clockkit::Server::new(4475);
+    /// server.start();
+    ///
+    /// let clock = clockkit::Config::default().port(4475).build_clock();
+    /// let packets = Arc::new(Mutex::new(Vec::new()));
+    /// let traced = Arc::clone(&packets);
+    /// clock.trace_packets(move |direction, packet| {
+    ///     traced.lock().unwrap().push((direction, packet.kind()))
+    /// });
+    /// clock.start();
+    /// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+    /// clock.stop();
+    ///
+    /// let packets = packets.lock().unwrap();
+    /// assert!(packets.contains(&(PacketDirection::Sent, PacketType::Request)));
+    /// assert!(packets.contains(&(PacketDirection::Received, PacketType::Reply)));
+    /// assert!(packets.contains(&(PacketDirection::Sent, PacketType::Acknowledge)));
+    /// ```
+    pub fn trace_packets(
+        &self,
+        trace: impl Fn(PacketDirection, ClockPacket) + Send + Sync + 'static,
+    ) {
+        ffi::setPacketTrace(self.ptr.clone(), trace_box(trace));
+    }
+}
+
+impl ClockClient {
+    /// Call `trace` with every packet the client exchanges with the server, see
+    /// [`PhaseLockedClock::trace_packets`]. Requires the `packet_trace` feature.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::{self, ClockClient};
+    /// let server = clockkit::Server::new(4476);
+    /// server.start();
+    /// # std::thread::sleep(std::time::Duration::from_millis(50));
+    ///
+    /// let client = ClockClient::new("127.0.0.1".to_string(), 4476, 100000);
+    /// client.trace_packets(clockkit::packet_writer(std::io::stderr()));
+    /// client.query_raw().unwrap();
+    /// ```
+    pub fn trace_packets(
+        &self,
+        trace: impl Fn(PacketDirection, ClockPacket) + Send + Sync + 'static,
+    ) {
+        ffi::setClientPacketTrace(self.ptr.clone(), trace_box(trace));
+    }
+}
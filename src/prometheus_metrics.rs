@@ -0,0 +1,89 @@
+//! Export of a clock's state to Prometheus, requires the `prometheus` feature.
+
+use crate::{Callback, PhaseLockedClock, Update};
+use prometheus::{IntCounter, IntGauge, Registry};
+use std::sync::{Arc, Mutex};
+
+/// Register gauges and counters for `clock` in `registry`, kept up to date by the clock's
+/// monitor thread every 100ms while it runs.
+///
+/// The metrics are:
+/// - `clockkit_offset_microseconds`: offset as by [`PhaseLockedClock::get_offset`], keeping the
+///   last value while out of sync,
+/// - `clockkit_rtt_microseconds`: round trip time of the last successful request,
+/// - `clockkit_synchronized`: 1 while synchronized, else 0,
+/// - `clockkit_updates_total` and `clockkit_timeouts_total`: see [`crate::Metrics`].
+///
+/// The names are fixed, so registering a second clock in the same registry fails with
+/// [`prometheus::Error::AlreadyReg`]; give each clock a registry of its own, e.g. with
+/// [`Registry::new_custom`] and a prefix or labels.
+///
+/// Example:
+/// ```
+/// # use clockkit;
+/// # use prometheus::{Encoder, TextEncoder};
+/// # use std::time::Duration;
+/// let server = clockkit::Server::new(4480);
+/// server.start();
+///
+/// let clock = clockkit::Config::default().port(4480).build_clock();
+/// let registry = prometheus::Registry::new();
+/// clockkit::register_metrics(&clock, &registry).unwrap();
+/// clock.start();
+/// clock.wait_until_synchronized(Duration::from_secs(5)).unwrap();
+/// std::thread::sleep(Duration::from_millis(500));
+///
+/// let mut text = Vec::new();
+/// TextEncoder::new().encode(&registry.gather(), &mut text).unwrap();
+/// let text = String::from_utf8(text).unwrap();
+/// assert!(text.contains("clockkit_synchronized 1"));
+/// assert!(text.contains("clockkit_updates_total"));
+/// ```
+pub fn register_metrics(
+    clock: &PhaseLockedClock,
+    registry: &Registry,
+) -> Result<(), prometheus::Error> {
+    let offset = IntGauge::new(
+        "clockkit_offset_microseconds",
+        "Phase offset of the reference clock relative to the local clock",
+    )?;
+    let rtt = IntGauge::new(
+        "clockkit_rtt_microseconds",
+        "Round trip time of the last successful request",
+    )?;
+    let synchronized = IntGauge::new("clockkit_synchronized", "1 if the clock is synchronized")?;
+    let updates = IntCounter::new(
+        "clockkit_updates_total",
+        "Successful requests to the server",
+    )?;
+    let timeouts = IntCounter::new(
+        "clockkit_timeouts_total",
+        "Requests without a valid reply in time",
+    )?;
+    registry.register(Box::new(offset.clone()))?;
+    registry.register(Box::new(rtt.clone()))?;
+    registry.register(Box::new(synchronized.clone()))?;
+    registry.register(Box::new(updates.clone()))?;
+    registry.register(Box::new(timeouts.clone()))?;
+
+    let metrics = clock.metrics.clone();
+    let watcher = move |update: Update| {
+        if let Some(usec) = update.offset {
+            offset.set(usec);
+        }
+        rtt.set(update.rtt.as_micros().try_into().unwrap_or(i64::MAX));
+        synchronized.set(i64::from(update.synchronized));
+        // The monitor refreshed the metrics right before, they only grow.
+        let advance = |counter: &IntCounter, total: u64| {
+            counter.inc_by(total.saturating_sub(counter.get()));
+        };
+        advance(&updates, metrics.updates());
+        advance(&timeouts, metrics.timeouts());
+    };
+    let watcher: Callback = Arc::new(Mutex::new(Box::new(watcher)));
+    match clock.watchers.lock() {
+        Ok(mut watchers) => watchers.push(watcher),
+        Err(poisoned) => poisoned.into_inner().push(watcher),
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! Timestamps as used by clockkit, i.e. μs since the Unix epoch.
+//!
+//! This only depends on `core`, so that the math can be reused without `std`; the
+//! `no_std_timestamp` example checks that. Conversions to `std`, `chrono` and `time` types live in
+//! the crate root.
+
+use core::{ops::Sub, time::Duration};
+
+/// A point in time as μs since the Unix epoch, the resolution of clockkit.
+///
+/// Example:
+/// ```
+/// # use clockkit::Timestamp;
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// let before = Timestamp::from_micros(-1);
+/// let after = Timestamp::from_micros(1_500_000);
+/// assert_eq!(after - before, Duration::from_micros(1_500_001));
+/// // Like `Instant`, the difference saturates at zero.
+/// assert_eq!(before - after, Duration::ZERO);
+///
+/// assert_eq!(
+///     before.to_system_time().unwrap(),
+///     UNIX_EPOCH - Duration::from_micros(1)
+/// );
+/// # #[cfg(feature = "chrono")]
+/// # {
+/// assert_eq!(
+///     before.to_datetime().unwrap().to_rfc3339(),
+///     "1969-12-31T23:59:59.999999+00:00"
+/// );
+/// assert_eq!(
+///     Timestamp::from_micros(1_234_567).to_datetime().unwrap().to_rfc3339(),
+///     "1970-01-01T00:00:01.234567+00:00"
+/// );
+/// # }
+/// ```
+///
+/// Timestamps order chronologically, e.g. to merge the readings of several sensors:
+/// ```
+/// # use clockkit::Timestamp;
+/// # use std::collections::BTreeMap;
+/// let mut stamps: Vec<_> = [3, -2, 1_000_000, 0, -1_000_000]
+///     .map(Timestamp::from_micros)
+///     .into();
+/// stamps.sort();
+/// assert_eq!(
+///     stamps.iter().map(Timestamp::as_micros).collect::<Vec<_>>(),
+///     [-1_000_000, -2, 0, 3, 1_000_000]
+/// );
+///
+/// let events = BTreeMap::from([
+///     (Timestamp::from_micros(20), "camera"),
+///     (Timestamp::from_micros(10), "gamepad"),
+/// ]);
+/// assert_eq!(events.values().collect::<Vec<_>>(), [&"gamepad", &"camera"]);
+/// assert!(Timestamp::from_micros(-1) < Timestamp::from_micros(0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(i64);
+
+impl Timestamp {
+    pub const fn from_micros(usec: i64) -> Self {
+        Self(usec)
+    }
+
+    pub const fn as_micros(&self) -> i64 {
+        self.0
+    }
+
+    /// Split into whole seconds since the epoch and the nanoseconds within that second.
+    ///
+    /// Timestamps before the epoch borrow a second, so that e.g. -1 μs becomes -1 s + 999999000 ns.
+    pub const fn as_secs_nanos(&self) -> (i64, u32) {
+        let sec = self.0.div_euclid(1_000_000);
+        // rem_euclid is always in 0..1_000_000, so this fits into u32.
+        let us = self.0.rem_euclid(1_000_000) as u32;
+        (sec, us * 1000)
+    }
+
+    /// The distance from the epoch, and whether the timestamp is before it.
+    pub const fn abs_since_epoch(&self) -> (Duration, bool) {
+        (Duration::from_micros(self.0.unsigned_abs()), self.0 < 0)
+    }
+}
+
+impl From<Timestamp> for Duration {
+    /// The time since the epoch, saturating at zero like [`Sub`] for timestamps before it, see
+    /// [`Timestamp::abs_since_epoch`] for those.
+    ///
+    /// Example:
+    /// ```
+    /// # use clockkit::Timestamp;
+    /// # use std::time::Duration;
+    /// assert_eq!(Duration::from(Timestamp::from_micros(1_500_000)), Duration::from_millis(1500));
+    /// assert_eq!(Duration::from(Timestamp::from_micros(-1)), Duration::ZERO);
+    /// ```
+    fn from(ts: Timestamp) -> Self {
+        ts - Timestamp::from_micros(0)
+    }
+}
+
+impl Sub for Timestamp {
+    type Output = Duration;
+
+    /// The time elapsed from `rhs` to `self`, zero if `rhs` is later.
+    fn sub(self, rhs: Self) -> Duration {
+        let usec = i128::from(self.0) - i128::from(rhs.0);
+        // The difference of two i64 always fits into u64 once it's positive.
+        Duration::from_micros(usec.max(0) as u64)
+    }
+}